@@ -0,0 +1,337 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		cache.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::cell::{ Cell, RefCell };
+use std::collections::HashMap;
+use std::io;
+use std::io::{ BufRead, Write };
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use mpdsh::{ Ack, AckCode, ListEntry };
+
+/// The first line of a cache file, the `db_update` of `stats` the listings belong to.
+const STAMP_KEY : &str = "db_update";
+
+/// Starts a listing in a cache file, followed by its entries as MPD sends them.
+const LISTING_KEY : &str = "mpdsh_listing";
+
+/// `cache` in config.toml.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode
+{
+    /// Every listing asks MPD.
+    Off
+,   #[default]
+    Memory
+,   /// Kept in a file for each server, and used again while the database is the same.
+    Disk
+}
+
+/// The listings of the database ( `listfiles "Jazz"`, `listall ""` ... ) by command line,
+/// so completion and wildcards do not ask MPD again. Dropped when the database changes.
+pub struct DirCache
+{
+    mode:       Mode
+,   entries:    RefCell< HashMap< String, Result< Vec< ListEntry >, Ack > > >
+,   /// `Client::database_version` the entries belong to.
+    version:    Cell< u64 >
+,   /// The `db_update` of `stats` the entries belong to, for the file.
+    stamp:      Cell< Option< u64 > >
+,   path:       Option< PathBuf >
+}
+
+impl DirCache
+{
+    /// With `Mode::Disk`, `server` names the file, in `$XDG_CACHE_HOME/mpdsh/` ( `~/.cache/mpdsh/` ).
+    pub fn new( mode: Mode, server: &str ) -> DirCache
+    {
+        let name : String = server.chars().map( |c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' } ).collect();
+
+        DirCache
+        {
+            mode
+        ,   entries:    RefCell::new( HashMap::new() )
+        ,   version:    Cell::new( 0 )
+        ,   stamp:      Cell::new( None )
+        ,   path:       if mode == Mode::Disk { dirs::cache_dir().map( |x| x.join( "mpdsh" ).join( name ) ) } else { None }
+        }
+    }
+
+    /// The listing of `cmd`, unless the database changed since ( `version` is not the same ).
+    pub fn get( &self, cmd: &str, version: u64 ) -> Option< Result< Vec< ListEntry >, Ack > >
+    {
+        if self.version.replace( version ) != version
+        {
+            self.clear();
+        }
+
+        self.entries.borrow().get( cmd ).cloned()
+    }
+
+    /// Keeps the listing of `cmd`. A missing directory is kept too, completion asks for it again and again.
+    pub fn put( &self, cmd: String, result: &Result< Vec< ListEntry >, Ack > )
+    {
+        let keep = match result
+        {
+            Ok(_)       => true
+        ,   Err( x )    => x.code == AckCode::NoExist
+        };
+
+        if keep && self.mode != Mode::Off
+        {
+            self.entries.borrow_mut().insert( cmd, result.clone() );
+        }
+    }
+
+    /// Drops the entries if they belong to another `db_update`. For a client without the idle listener,
+    /// which asks `stats` before each listing instead.
+    pub fn check_stamp( &self, db_update: u64 )
+    {
+        if self.stamp.get() != Some( db_update )
+        {
+            self.clear();
+            self.stamp.set( Some( db_update ) );
+        }
+    }
+
+    pub fn mode( &self ) -> Mode
+    {
+        self.mode
+    }
+
+    pub fn clear( &self )
+    {
+        self.entries.borrow_mut().clear();
+        self.stamp.set( None );
+    }
+
+    /// True if the entries to come need the `db_update` of `stats`, to be saved.
+    pub fn needs_stamp( &self ) -> bool
+    {
+        self.path.is_some() && self.stamp.get().is_none()
+    }
+
+    pub fn set_stamp( &self, db_update: u64 )
+    {
+        self.stamp.set( Some( db_update ) );
+    }
+
+    /// Reads the file, if it was written for the database of `db_update`. The listings to come belong to it as well.
+    pub fn load( &self, db_update: u64 ) -> io::Result< () >
+    {
+        let path = match &self.path
+        {
+            Some( x )   => x
+        ,   None        => return Ok( () )
+        };
+
+        self.stamp.set( Some( db_update ) );
+
+        let f = match std::fs::File::open( path )
+        {
+            Ok( x )                                             => x
+        ,   Err( x ) if x.kind() == io::ErrorKind::NotFound     => return Ok( () )
+        ,   Err( x )                                            => return Err( x )
+        };
+
+        let mut lines = io::BufReader::new( f ).lines();
+
+        match lines.next().transpose()?.as_deref().and_then( |x| x.split_once( ": " ) )
+        {
+            Some( ( k, v ) ) if k == STAMP_KEY && v.parse() == Ok( db_update ) => {}
+        ,   _ => return Ok( () )
+        }
+
+        let mut listings = Vec::< ( String, Vec< ( String, String ) > ) >::new();
+
+        for line in lines
+        {
+            let line = line?;
+
+            match line.split_once( ": " )
+            {
+                Some( ( k, v ) ) if k == LISTING_KEY    => listings.push( ( String::from( v ), Vec::new() ) )
+            ,   Some( ( k, v ) )                        => if let Some( x ) = listings.last_mut() { x.1.push( ( String::from( k ), String::from( v ) ) ) }
+            ,   None                                    => {}
+            }
+        }
+
+        let mut entries = self.entries.borrow_mut();
+
+        for ( cmd, flds ) in listings
+        {
+            entries.insert( cmd, Ok( ListEntry::list_from_fields( flds ) ) );
+        }
+
+        Ok( () )
+    }
+
+    /// Writes the listings to the file. The missing directories are not written.
+    pub fn save( &self ) -> io::Result< () >
+    {
+        let ( path, stamp ) = match ( &self.path, self.stamp.get() )
+        {
+            ( Some( x ), Some( y ) )    => ( x, y )
+        ,   _                           => return Ok( () )
+        };
+
+        if let Some( x ) = path.parent()
+        {
+            std::fs::create_dir_all( x )?;
+        }
+
+        let mut w = io::BufWriter::new( std::fs::File::create( path )? );
+
+        writeln!( w, "{}: {}", STAMP_KEY, stamp )?;
+
+        for ( cmd, entries ) in self.entries.borrow().iter()
+        {
+            if let Ok( entries ) = entries
+            {
+                writeln!( w, "{}: {}", LISTING_KEY, cmd )?;
+
+                for x in entries
+                {
+                    writeln!( w, "{}: {}", x.kind, x.name )?;
+
+                    for ( k, v ) in &x.flds
+                    {
+                        writeln!( w, "{}: {}", k, v )?;
+                    }
+                }
+            }
+        }
+
+        w.flush()
+    }
+
+    /// The file, for the messages.
+    pub fn path( &self ) -> Option< &PathBuf >
+    {
+        self.path.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn fields( x: &[ ( &str, &str ) ] ) -> Vec< ( String, String ) >
+    {
+        x.iter().map( |( k, v )| ( String::from( *k ), String::from( *v ) ) ).collect()
+    }
+
+    fn listing( x: &[ ( &str, &str ) ] ) -> Result< Vec< ListEntry >, Ack >
+    {
+        Ok( ListEntry::list_from_fields( fields( x ) ) )
+    }
+
+    /// The entries as text, to compare.
+    fn show( x: Option< Result< Vec< ListEntry >, Ack > > ) -> Option< Vec< String > >
+    {
+        x.and_then( |x| x.ok() ).map( |x| x.iter().map( |e| format!( "{} {} {:?}", e.kind, e.name, e.flds ) ).collect() )
+    }
+
+    /// A disk cache in a file of its own.
+    fn disk( name: &str ) -> DirCache
+    {
+        let path = std::env::temp_dir().join( format!( "mpdsh-cache-test-{}-{}", std::process::id(), name ) );
+
+        let _ = std::fs::remove_file( &path );
+
+        DirCache { path: Some( path ), ..DirCache::new( Mode::Disk, name ) }
+    }
+
+    #[test]
+    fn dropped_when_the_version_changes()
+    {
+        let cache = DirCache::new( Mode::Memory, "localhost:6600" );
+
+        cache.put( String::from( "listfiles" ), &listing( &[ ( "directory", "Jazz" ) ] ) );
+
+        assert!( cache.get( "listfiles", 0 ).is_some() );
+        assert!( cache.get( "listfiles Rock", 0 ).is_none() );
+        assert!( cache.get( "listfiles", 1 ).is_none() );
+        assert!( cache.get( "listfiles", 0 ).is_none() );
+    }
+
+    #[test]
+    fn keeps_what_it_should()
+    {
+        let cache = DirCache::new( Mode::Memory, "localhost:6600" );
+
+        cache.put( String::from( "listfiles Nope" ), &Err( Ack::new( AckCode::NoExist, None ) ) );
+        cache.put( String::from( "listfiles Secret" ), &Err( Ack::new( AckCode::Permission, None ) ) );
+
+        assert!( matches!( cache.get( "listfiles Nope", 0 ), Some( Err(_) ) ) );
+        assert!( cache.get( "listfiles Secret", 0 ).is_none() );
+
+        let off = DirCache::new( Mode::Off, "localhost:6600" );
+
+        off.put( String::from( "listfiles" ), &listing( &[ ( "directory", "Jazz" ) ] ) );
+
+        assert!( off.get( "listfiles", 0 ).is_none() );
+        assert!( !off.needs_stamp() );
+    }
+
+    #[test]
+    fn dropped_when_db_update_changes()
+    {
+        let cache = DirCache::new( Mode::Memory, "localhost:6600" );
+
+        cache.check_stamp( 5 );
+        cache.put( String::from( "listfiles" ), &listing( &[ ( "directory", "Jazz" ) ] ) );
+        cache.check_stamp( 5 );
+
+        assert!( cache.get( "listfiles", 0 ).is_some() );
+
+        cache.check_stamp( 6 );
+
+        assert!( cache.get( "listfiles", 0 ).is_none() );
+    }
+
+    #[test]
+    fn file_round_trip()
+    {
+        let entries = &[ ( "directory", "Jazz/Live: 1961" ), ( "Last-Modified", "2020-01-01T00:00:00Z" ), ( "file", "Jazz/a: b.flac" ), ( "Title", "So What: Take 2" ) ];
+        let cmd     = "lsinfo \"Jazz: Live\"";
+
+        let a = disk( "round-trip" );
+
+        assert!( a.needs_stamp() );
+
+        // no file yet
+        a.load( 7 ).unwrap();
+
+        assert!( !a.needs_stamp() );
+
+        a.put( String::from( cmd ), &listing( entries ) );
+        a.put( String::from( "listfiles Nope" ), &Err( Ack::new( AckCode::NoExist, None ) ) );
+        a.save().unwrap();
+
+        let b = DirCache { path: a.path.clone(), ..DirCache::new( Mode::Disk, "b" ) };
+
+        b.load( 7 ).unwrap();
+
+        assert_eq!( show( b.get( cmd, 0 ) ), show( Some( listing( entries ) ) ) );
+        assert!( b.get( "listfiles Nope", 0 ).is_none() );
+
+        // another database
+        let c = DirCache { path: a.path.clone(), ..DirCache::new( Mode::Disk, "c" ) };
+
+        c.load( 8 ).unwrap();
+
+        assert!( c.get( cmd, 0 ).is_none() );
+
+        let _ = std::fs::remove_file( a.path().unwrap() );
+    }
+}
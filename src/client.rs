@@ -0,0 +1,925 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		client.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::io;
+use std::io::BufReader;
+use std::borrow::Cow;
+use std::thread;
+use std::time::Duration;
+use std::sync::{ mpsc, Arc, Mutex };
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+use crate::error::{ Ack, AckCode };
+use crate::protocol::{ ExecOk, ExecResult, BinaryResult, LIST_OK, open, transact, quote_arg, make_command_1, make_command_2 };
+use crate::stream::MpdStream;
+use crate::types::{ ListEntry, Song, Status, PlayState, Output, Playlist, Stats };
+
+/// The results of exec_command_batch(), or the index of the failed command and its error.
+type BatchResult = Result< Vec<ExecOk>, ( usize, Ack ) >;
+
+/// Number of commands sent in one command list by exec_command_batch().
+const COMMAND_LIST_CHUNK: usize = 256;
+
+/// Waits between reconnect attempts.
+const RECONNECT_BACKOFF_MS: [u64; 6] = [ 500, 1000, 2000, 4000, 8000, 16000 ];
+
+/// The last accepted `password` command. Shared by the worker and the idle listener,
+/// both send it again after reconnecting.
+type SharedPassword = Arc< Mutex< Option<String> > >;
+
+/// Counts the `database`, `update` and `stored_playlist` changes seen by the idle listener.
+type DbVersion = Arc< AtomicU64 >;
+
+/// Shuts down the connection of the idle listener, to end a blocking `idle`.
+type IdleShutdown = Arc< Mutex< Option< Box< dyn Fn() + Send > > > >;
+
+/// Ends the idle listener. Dropping `_tx` wakes it from its waits, `shutdown` from `idle`.
+struct IdleStop
+{
+    _tx:        mpsc::Sender<()>
+,   shutdown:   IdleShutdown
+}
+
+/// Receives one-line change notices from the idle listener.
+pub type IdleNotify = Box< dyn FnMut( String ) + Send >;
+
+/// Receives the progress of the worker when the connection drops ( `reconnecting... localhost:6600` ).
+/// The library prints nothing itself.
+pub type ConnNotify = Box< dyn FnMut( String ) + Send >;
+
+/// Chunk size for albumart/readpicture, set on every worker connection ( MPD 0.22.4+, ignored by older ones ).
+const BINARY_LIMIT_CMD: &str = "binarylimit 1048576";
+
+/// The idle command sent by the idle listener.
+const IDLE_COMMAND: &str = "idle player playlist mixer options database update stored_playlist output";
+
+/// A connection to MPD. Commands are run by a worker thread, which keeps the
+/// connection alive with `ping` and reconnects when it drops.
+pub struct Client
+{
+    worker_handle:  Option< thread::JoinHandle<()> >
+,   htx:            mpsc::Sender<String>
+,   wrx:            mpsc::Receiver<ExecResult>
+,   version:        String
+,   /// None without the idle listener.
+    db_version:     Option< DbVersion >
+,   idle_stop:      Option< IdleStop >
+}
+
+impl Client
+{
+    /// Connects to `addr`. With `notify`, a second connection runs `idle` and reports changes to it.
+    /// `conn_notify` is told when the connection drops and when it is back.
+    pub fn connect<S: MpdStream>( addr: &S::Addr, protolog: bool, notify: Option< IdleNotify >, conn_notify: Option< ConnNotify > ) -> io::Result< Self >
+    {
+        let ( mut reader, version ) = open::<S>( addr )?;
+
+        let _ = transact( &mut reader, BINARY_LIMIT_CMD, protolog )?;
+
+        let ( htx, hrx ) : ( mpsc::Sender<String>,      mpsc::Receiver<String> )        = mpsc::channel();
+        let ( wtx, wrx ) : ( mpsc::Sender<ExecResult>,  mpsc::Receiver<ExecResult> )    = mpsc::channel();
+
+        let password_cmd : SharedPassword = Arc::new( Mutex::new( None ) );
+        let ( db_version, idle_stop ) = match notify
+        {
+            Some( notify ) =>
+            {
+                let addr = addr.clone();
+                let password_cmd = password_cmd.clone();
+                let db_version : DbVersion = Arc::new( AtomicU64::new( 0 ) );
+                let ( stop_tx, stop_rx ) = mpsc::channel::<()>();
+                let shutdown : IdleShutdown = Arc::new( Mutex::new( None ) );
+
+                let ret = ( Some( db_version.clone() ), Some( IdleStop { _tx: stop_tx, shutdown: shutdown.clone() } ) );
+
+                thread::spawn( move ||
+                    {
+                        Self::idle_listener::<S>( addr, password_cmd, db_version, notify, stop_rx, shutdown )
+                    }
+                );
+
+                ret
+            }
+        ,   None => ( None, None )
+        };
+
+        let addr = addr.clone();
+
+        let worker_handle  = thread::spawn( move ||
+            {
+                Self::worker::<S>( addr, reader, password_cmd, hrx, wtx, protolog, conn_notify )
+            }
+        );
+
+        Ok( Client
+            {
+                worker_handle:  Some( worker_handle )
+            ,   htx
+            ,   wrx
+            ,   version
+            ,   db_version
+            ,   idle_stop
+            }
+        )
+    }
+
+    /// The protocol version from the `OK MPD <version>` greeting.
+    pub fn version( &self ) -> &str
+    {
+        &self.version
+    }
+
+    /// Changes each time the idle listener sees the database or the stored playlists change.
+    /// None without `notify`, nothing tells the database changed then ( `stats` does, `db_update` ).
+    pub fn database_version( &self ) -> Option< u64 >
+    {
+        self.db_version.as_ref().map( |x| x.load( Ordering::Relaxed ) )
+    }
+
+    /// Runs one raw command line, e.g. `lsinfo "Jazz"`.
+    pub fn exec_command( &self, cmd: &str ) -> ExecResult
+    {
+        if self.htx.send( String::from( cmd ) ).is_ok()
+        {
+            if let Ok(x) = self.wrx.recv()
+            {
+                return x;
+            }
+        }
+
+        Err( Ack::new( AckCode::Worker, None ) )
+    }
+
+    /// Ends the session and waits for the worker thread.
+    pub fn close( &mut self ) -> Result< (), Ack >
+    {
+        self.stop_idle();
+        self.exec_command( "quit" )?;
+
+        if let Some( x ) = self.worker_handle.take()
+        {
+            let _ = x.join();
+        }
+
+        Ok( () )
+    }
+
+    /// Ends the idle listener and closes its connection. Also done on drop.
+    fn stop_idle( &mut self )
+    {
+        if let Some( x ) = self.idle_stop.take()
+        {
+            let IdleStop { _tx, shutdown } = x;
+
+            drop( _tx );
+
+            let f = shutdown.lock().unwrap().take();
+
+            if let Some( f ) = f
+            {
+                f();
+            }
+        }
+    }
+
+    /// Runs `cmds` as one command list. With `list_ok` ( command_list_ok_begin ) the result
+    /// has one ExecOk per command, otherwise ( command_list_begin ) a single one.
+    /// On error, Ack.cmd_index is the index of the failed command in `cmds`.
+    pub fn exec_command_list( &self, cmds: &[String], list_ok: bool ) -> Result< Vec<ExecOk>, Ack >
+    {
+        let mut cmd = String::from( if list_ok { "command_list_ok_begin" } else { "command_list_begin" } );
+
+        for x in cmds
+        {
+            cmd.push( '\n' );
+            cmd.push_str( x );
+        }
+
+        cmd.push_str( "\ncommand_list_end" );
+
+        let x = self.exec_command( &cmd )?;
+
+        Ok( if list_ok { split_list_ok( x.flds ) } else { vec![ x ] } )
+    }
+
+    /// Runs many commands as command lists of COMMAND_LIST_CHUNK commands.
+    /// On error, returns the index in `cmds` of the command that failed. The commands before it have been executed.
+    pub fn exec_command_batch( &self, cmds: &[String], list_ok: bool ) -> BatchResult
+    {
+        run_chunks( cmds, |x| self.exec_command_list( x, list_ok ) )
+    }
+
+    /// Reads a whole binary object with `cmd` ( albumart or readpicture ), chunk by chunk.
+    /// Returns the data and the `type` field if MPD sent one. An empty response ( no picture ) is `Ok( None )`.
+    pub fn exec_binary( &self, cmd: &str, uri: &str ) -> BinaryResult
+    {
+        read_binary( |offset| self.exec_command( &make_command_2( cmd, uri, &offset.to_string() ) ) )
+    }
+
+    pub fn status( &self ) -> Result< Status, Ack >
+    {
+        Ok( Status::from_fields( self.exec_command( "status" )?.flds ) )
+    }
+
+    pub fn current_song( &self ) -> Result< Option< Song >, Ack >
+    {
+        Ok( Song::from_fields( self.exec_command( "currentsong" )?.flds ) )
+    }
+
+    /// The queue ( `playlistinfo` ).
+    pub fn queue( &self ) -> Result< Vec< Song >, Ack >
+    {
+        Ok( Song::list_from_fields( self.exec_command( "playlistinfo" )?.flds ) )
+    }
+
+    pub fn outputs( &self ) -> Result< Vec< Output >, Ack >
+    {
+        Ok( Output::list_from_fields( self.exec_command( "outputs" )?.flds ) )
+    }
+
+    /// The stored playlists ( `listplaylists` ).
+    pub fn playlists( &self ) -> Result< Vec< Playlist >, Ack >
+    {
+        Ok( Playlist::list_from_fields( self.exec_command( "listplaylists" )?.flds ) )
+    }
+
+    /// The songs of the stored playlist ( `listplaylistinfo` ).
+    pub fn playlist_songs( &self, name: &str ) -> Result< Vec< Song >, Ack >
+    {
+        Ok( Song::list_from_fields( self.exec_command( &make_command_1( "listplaylistinfo", name ) )?.flds ) )
+    }
+
+    pub fn stats( &self ) -> Result< Stats, Ack >
+    {
+        Ok( Stats::from_fields( self.exec_command( "stats" )?.flds ) )
+    }
+
+    /// Directories, songs ( with tags ) and playlists in `path`.
+    pub fn lsinfo( &self, path: &str ) -> Result< Vec< ListEntry >, Ack >
+    {
+        Ok( ListEntry::list_from_fields( self.exec_command( &make_command_1( "lsinfo", path ) )?.flds ) )
+    }
+
+    /// Directories and files in `path`, without tags.
+    pub fn listfiles( &self, path: &str ) -> Result< Vec< ListEntry >, Ack >
+    {
+        Ok( ListEntry::list_from_fields( self.exec_command( &make_command_1( "listfiles", path ) )?.flds ) )
+    }
+
+    /// Every directory and file under `path`, recursively, without tags.
+    pub fn listall( &self, path: &str ) -> Result< Vec< ListEntry >, Ack >
+    {
+        Ok( ListEntry::list_from_fields( self.exec_command( &make_command_1( "listall", path ) )?.flds ) )
+    }
+
+    /// Every directory and file under `path`, recursively, with tags.
+    pub fn listallinfo( &self, path: &str ) -> Result< Vec< ListEntry >, Ack >
+    {
+        Ok( ListEntry::list_from_fields( self.exec_command( &make_command_1( "listallinfo", path ) )?.flds ) )
+    }
+
+    /// Songs of the database matching `args` ( `TYPE WHAT ...` or a filter expression ).
+    pub fn find( &self, args: &[String] ) -> Result< Vec< Song >, Ack >
+    {
+        self.find_with( "find", args )
+    }
+
+    /// Like `find`, ignoring case.
+    pub fn search( &self, args: &[String] ) -> Result< Vec< Song >, Ack >
+    {
+        self.find_with( "search", args )
+    }
+
+    fn find_with( &self, cmd1: &str, args: &[String] ) -> Result< Vec< Song >, Ack >
+    {
+        let mut cmd = String::from( cmd1 );
+
+        for x in args
+        {
+            cmd.push( ' ' );
+            cmd.push_str( &quote_arg( x ) );
+        }
+
+        Ok( Song::list_from_fields( self.exec_command( &cmd )?.flds ) )
+    }
+
+    /// The tag types the server supports ( `tagtypes` ).
+    pub fn tagtypes( &self ) -> Result< Vec< String >, Ack >
+    {
+        Ok( self.exec_command( "tagtypes" )?.flds.into_iter().filter( |x| x.0 == "tagtype" ).map( |x| x.1 ).collect() )
+    }
+
+    /// The values of the tag in the database ( `list TYPE [FILTER]` ), of the songs matching the filter expression.
+    pub fn list_tag( &self, tag: &str, filter: Option< &str > ) -> Result< Vec< String >, Ack >
+    {
+        let cmd = match filter
+        {
+            Some( x )   => make_command_2( "list", tag, x )
+        ,   None        => make_command_1( "list", tag )
+        };
+
+        Ok( self.exec_command( &cmd )?.flds.into_iter().map( |x| x.1 ).filter( |x| !x.is_empty() ).collect() )
+    }
+
+    /// The commands the current session may use.
+    pub fn commands( &self ) -> Result< Vec< String >, Ack >
+    {
+        Ok( self.exec_command( "commands" )?.flds.into_iter().filter( |x| x.0 == "command" ).map( |x| x.1 ).collect() )
+    }
+
+    /// Sends the password. It is sent again after every reconnect.
+    pub fn password( &self, password: &str ) -> Result< (), Ack >
+    {
+        self.exec_command( &make_command_1( "password", password ) ).map( |_| () )
+    }
+
+    fn worker<S: MpdStream>( addr: S::Addr, reader: BufReader<S>, password_cmd: SharedPassword, hrx : mpsc::Receiver<String>, wtx : mpsc::Sender<ExecResult>, protolog : bool, mut conn_notify: Option< ConnNotify > )
+    {
+        let mut conn = Some( reader );
+
+        let time_out = Duration::from_secs( 10 );
+
+        loop
+        {
+            let recv = hrx.recv_timeout( time_out );
+            let ( cmd, ret ) = match recv
+            {
+                Ok( x ) => { ( Cow::Owned( x )          , true ) }
+            ,   Err( mpsc::RecvTimeoutError::Timeout )
+                        => { ( Cow::Borrowed( "ping" )  , false ) }
+            ,   Err( mpsc::RecvTimeoutError::Disconnected )
+                        => { break; }
+            };
+
+            if cmd == "quit"
+            {
+                let _ = wtx.send(
+                    Ok(
+                        ExecOk
+                        {
+                            flds:   Vec::<(String, String)>::new()
+                        ,   bin:    Option::<Vec<u8>>::None
+                        }
+                    )
+                );
+
+                break;
+            }
+
+            if conn.is_none()
+            {
+                if !ret
+                {
+                    continue;
+                }
+
+                conn = Self::reconnect::<S>( &addr, &password_cmd, protolog, &mut conn_notify );
+
+                if conn.is_none()
+                {
+                    let _ = wtx.send( Err( Ack::new( AckCode::Reconnect, Some( String::from( "not connected, reconnect failed." ) ) ) ) );
+                    continue;
+                }
+            }
+
+            let reader = conn.as_mut().unwrap();
+
+            match transact( reader, &cmd, protolog && ret )
+            {
+                Ok( x ) =>
+                {
+                    if x.is_ok() && cmd.starts_with( "password " )
+                    {
+                        *password_cmd.lock().unwrap() = Some( cmd.to_string() );
+                    }
+
+                    if ret
+                    {
+                        let _ = wtx.send( x );
+                    }
+                }
+            ,   Err( x ) =>
+                {
+                    Self::tell( &mut conn_notify, format!( "connection lost. ({})", x ) );
+
+                    conn = Self::reconnect::<S>( &addr, &password_cmd, protolog, &mut conn_notify );
+
+                    if ret
+                    {
+                        let msg = if conn.is_some()
+                        {
+                            "connection lost, reconnected. the command was not completed."
+                        }
+                        else
+                        {
+                            "connection lost, reconnect failed."
+                        };
+
+                        let _ = wtx.send( Err( Ack::new( AckCode::Reconnect, Some( String::from( msg ) ) ) ) );
+                    }
+                }
+            }
+        }
+
+        if let Some( x ) = conn
+        {
+            if let Err(x) = x.get_ref().shutdown_both()
+            {
+                Self::tell( &mut conn_notify, format!( "{:?}", x ) );
+            }
+        }
+    }
+
+    /// Passes `msg` to `conn_notify`, if there is one.
+    fn tell( conn_notify: &mut Option< ConnNotify >, msg: String )
+    {
+        if let Some( x ) = conn_notify
+        {
+            x( msg );
+        }
+    }
+
+    /// Reconnects with backoff, and sends the password again.
+    fn reconnect<S: MpdStream>( addr: &S::Addr, password_cmd: &SharedPassword, protolog: bool, conn_notify: &mut Option< ConnNotify > ) -> Option< BufReader<S> >
+    {
+        for wait in RECONNECT_BACKOFF_MS.iter()
+        {
+            thread::sleep( Duration::from_millis( *wait ) );
+
+            Self::tell( conn_notify, format!( "reconnecting... {}", addr ) );
+
+            let mut reader = match open::<S>( addr )
+            {
+                Ok( ( x, _ ) )  => x
+            ,   Err(_)          => continue
+            };
+
+            let password = password_cmd.lock().unwrap().clone();
+
+            if let Some( x ) = password
+            {
+                match transact( &mut reader, &x, protolog )
+                {
+                    Ok( Ok(_) )     => {}
+                ,   Ok( Err( x ) )  => Self::tell( conn_notify, format!( "password rejected after reconnect. ({})", x ) )
+                ,   Err(_)          => continue
+                }
+            }
+
+            if transact( &mut reader, BINARY_LIMIT_CMD, protolog ).is_err()
+            {
+                continue;
+            }
+
+            Self::tell( conn_notify, format!( "reconnected. {}", addr ) );
+
+            return Some( reader );
+        }
+
+        None
+    }
+
+    /// Runs `idle` on a second connection and passes a notice for each change to `notify`.
+    /// Reconnects quietly and forever, the shell does not depend on this thread.
+    fn idle_listener<S: MpdStream>( addr: S::Addr, password_cmd: SharedPassword, db_version: DbVersion, mut notify: IdleNotify, stop: mpsc::Receiver<()>, shutdown: IdleShutdown )
+    {
+        let mut retry = 0;
+        let mut last_player = String::new();
+
+        // true once the Client is dropped, after waiting `ms` at most
+        let stopped = | ms: u64 | !matches!( stop.recv_timeout( Duration::from_millis( ms ) ), Err( mpsc::RecvTimeoutError::Timeout ) );
+
+        while !stopped( 0 )
+        {
+            let mut reader = match open::<S>( &addr )
+            {
+                Ok( ( x, _ ) ) => x
+            ,   Err(_) =>
+                {
+                    if stopped( RECONNECT_BACKOFF_MS[ retry ] )
+                    {
+                        return;
+                    }
+
+                    retry = ( retry + 1 ).min( RECONNECT_BACKOFF_MS.len() - 1 );
+                    continue;
+                }
+            };
+
+            retry = 0;
+
+            if let Ok( x ) = reader.get_ref().try_clone()
+            {
+                *shutdown.lock().unwrap() = Some( Box::new( move || { let _ = x.shutdown_both(); } ) );
+            }
+
+            // the Client may have been dropped before the shutdown was set
+            if stopped( 0 )
+            {
+                return;
+            }
+
+            // once for each connection, and again when it changed
+            let mut sent = None;
+
+            'conn: loop
+            {
+                let password = password_cmd.lock().unwrap().clone();
+
+                if password != sent
+                {
+                    if let Some( x ) = &password
+                    {
+                        if transact( &mut reader, x, false ).is_err()
+                        {
+                            break 'conn;
+                        }
+                    }
+
+                    sent = password;
+                }
+
+                match transact( &mut reader, IDLE_COMMAND, false )
+                {
+                    Ok( Ok( x ) ) =>
+                    {
+                        for ( k, v ) in x.flds
+                        {
+                            if k != "changed"
+                            {
+                                continue;
+                            }
+
+                            // the stored playlists are in the listings too ( lsinfo )
+                            if v == "database" || v == "update" || v == "stored_playlist"
+                            {
+                                db_version.fetch_add( 1, Ordering::Relaxed );
+                            }
+
+                            match Self::idle_notice( &mut reader, &v, &mut last_player )
+                            {
+                                Ok( Some( x ) ) => notify( x )
+                            ,   Ok( None )      => {}
+                            ,   Err(_)          => break 'conn
+                            }
+                        }
+                    }
+                ,   Ok( Err(_) ) =>
+                    {
+                        // e.g. no permission for `idle` until a password is given.
+                        if stopped( RECONNECT_BACKOFF_MS[ RECONNECT_BACKOFF_MS.len() - 1 ] )
+                        {
+                            return;
+                        }
+                    }
+                ,   Err(_) => break 'conn
+                }
+            }
+        }
+    }
+
+    /// Makes a one-line notice for a changed subsystem. `last_player` suppresses
+    /// repeated player notices ( e.g. seeking ) when the state and song are unchanged.
+    fn idle_notice<S: MpdStream>( reader: &mut BufReader<S>, subsystem: &str, last_player: &mut String ) -> io::Result< Option<String> >
+    {
+        let mut query = | cmd: &str | -> io::Result< Vec<(String, String)> >
+        {
+            Ok(
+                match transact( reader, cmd, false )?
+                {
+                    Ok( x )     => x.flds
+                ,   Err(_)      => Vec::<(String, String)>::new()
+                }
+            )
+        };
+
+        let msg = match subsystem
+        {
+            "player" =>
+            {
+                let st = Status::from_fields( query( "status" )? );
+
+                let msg = match st.state
+                {
+                    PlayState::Stop => String::from( "stopped" )
+                ,   x =>
+                    {
+                        let name = match Song::from_fields( query( "currentsong" )? )
+                        {
+                            Some( x )   => x.display_name()
+                        ,   None        => String::new()
+                        };
+
+                        if x == PlayState::Play
+                        {
+                            format!( "now playing: {}", name )
+                        }
+                        else
+                        {
+                            format!( "paused: {}", name )
+                        }
+                    }
+                };
+
+                if msg == *last_player
+                {
+                    return Ok( None );
+                }
+
+                *last_player = msg.clone();
+                msg
+            }
+        ,   "playlist" =>
+            {
+                let st = Status::from_fields( query( "status" )? );
+                format!( "playlist changed: {} songs", st.playlist_length )
+            }
+        ,   "mixer" =>
+            {
+                let st = Status::from_fields( query( "status" )? );
+                format!( "volume: {}", st.volume.map( |x| x.to_string() ).unwrap_or_default() )
+            }
+        ,   "options" =>
+            {
+                let st = Status::from_fields( query( "status" )? );
+                format!( "options: repeat {} random {} single {} consume {}"
+                    , st.repeat as i32
+                    , st.random as i32
+                    , st.single
+                    , st.consume
+                )
+            }
+        ,   "database" => String::from( "database updated" )
+        ,   "update" | "stored_playlist" => return Ok( None )
+        ,   "output" =>
+            {
+                let outputs : Vec<String> = Output::list_from_fields( query( "outputs" )? ).into_iter().map( |x|
+                    {
+                        format!( "{} {}", x.name, if x.enabled { "[on]" } else { "[off]" } )
+                    }
+                ).collect();
+
+                format!( "outputs: {}", outputs.join( ", " ) )
+            }
+        ,   _ => format!( "changed: {}", subsystem )
+        };
+
+        Ok( Some( format!( "* {}", msg ) ) )
+    }
+}
+
+/// Splits a command_list_ok_begin response at each LIST_OK, into one ExecOk per command.
+fn split_list_ok( flds: Vec<(String, String)> ) -> Vec<ExecOk>
+{
+    let mut ret = Vec::<ExecOk>::new();
+    let mut cur = Vec::<(String,String)>::new();
+
+    for ( k, v ) in flds
+    {
+        if k == LIST_OK
+        {
+            ret.push( ExecOk { flds: std::mem::take( &mut cur ), bin: None } );
+        }
+        else
+        {
+            cur.push( ( k, v ) );
+        }
+    }
+
+    ret
+}
+
+/// Passes `cmds` to `run` COMMAND_LIST_CHUNK at a time. On error, the index in `cmds` of the command that failed,
+/// the first of its chunk if the error is not from MPD ( Ack.cmd_index is not set then ).
+fn run_chunks< F: FnMut( &[String] ) -> Result< Vec<ExecOk>, Ack > >( cmds: &[String], mut run: F ) -> BatchResult
+{
+    let mut ret = Vec::<ExecOk>::new();
+
+    for ( i, chunk ) in cmds.chunks( COMMAND_LIST_CHUNK ).enumerate()
+    {
+        match run( chunk )
+        {
+            Ok( mut x ) => ret.append( &mut x )
+        ,   Err( x )    =>
+            {
+                let idx = i * COMMAND_LIST_CHUNK + if x.code.is_client_side() { 0 } else { x.cmd_index };
+                return Err( ( idx, x ) );
+            }
+        }
+    }
+
+    Ok( ret )
+}
+
+/// Reads a binary object a chunk at a time, `fetch` is given the offset of the next chunk.
+fn read_binary< F: FnMut( usize ) -> ExecResult >( mut fetch: F ) -> BinaryResult
+{
+    let mut data = Vec::<u8>::new();
+    let mut mime = Option::<String>::None;
+
+    loop
+    {
+        let x = fetch( data.len() )?;
+
+        let size = match x.flds.iter().find( |x| x.0 == "size" )
+        {
+            Some( ( _, v ) ) => match v.parse::<usize>()
+            {
+                Ok( x )     => x
+            ,   Err(_)      => return Err( Ack::new( AckCode::Binary, Some( format!( "bad size: {}", v ) ) ) )
+            }
+        ,   None if data.is_empty() => return Ok( None )
+        ,   None => return Err( Ack::new( AckCode::Binary, Some( String::from( "size is missing" ) ) ) )
+        };
+
+        if mime.is_none()
+        {
+            mime = x.flds.iter().find( |x| x.0 == "type" ).map( |x| x.1.clone() );
+        }
+
+        let chunk = x.bin.unwrap_or_default();
+
+        if chunk.is_empty() && data.len() < size
+        {
+            return Err( Ack::new( AckCode::Binary, Some( format!( "transfer stopped at {} of {} bytes", data.len(), size ) ) ) );
+        }
+
+        data.extend_from_slice( &chunk );
+
+        if data.len() >= size
+        {
+            if data.len() != size
+            {
+                return Err( Ack::new( AckCode::Binary, Some( format!( "received {} bytes, expected {}", data.len(), size ) ) ) );
+            }
+
+            return Ok( Some( ( data, mime ) ) );
+        }
+    }
+}
+
+impl Drop for Client
+{
+    fn drop( &mut self )
+    {
+        self.stop_idle();
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn fields( x: &[ ( &str, &str ) ] ) -> Vec<(String, String)>
+    {
+        x.iter().map( |( k, v )| ( String::from( *k ), String::from( *v ) ) ).collect()
+    }
+
+    #[test]
+    fn one_result_per_command()
+    {
+        let x = split_list_ok( fields( &[ ( "Id", "1" ), ( LIST_OK, "" ), ( LIST_OK, "" ), ( "Id", "3" ), ( "x", "y" ), ( LIST_OK, "" ) ] ) );
+
+        assert_eq!( x.len(), 3 );
+        assert_eq!( x[0].flds, fields( &[ ( "Id", "1" ) ] ) );
+        assert!( x[1].flds.is_empty() );
+        assert_eq!( x[2].flds, fields( &[ ( "Id", "3" ), ( "x", "y" ) ] ) );
+
+        assert!( split_list_ok( Vec::new() ).is_empty() );
+    }
+
+    /// Runs `cmds` in chunks, MPD failing at the command `bad`.
+    fn run( cmds: &[String] ) -> ( Vec<usize>, BatchResult )
+    {
+        let mut sizes = Vec::<usize>::new();
+
+        let ret = run_chunks( cmds, |x|
+            {
+                sizes.push( x.len() );
+
+                match x.iter().position( |x| x == "bad" )
+                {
+                    Some( i )   => Err( Ack { code: AckCode::NoExist, cmd_index: i, cur_cmd: None, msg_text: None } )
+                ,   None        => Ok( vec![ ExecOk::default(); x.len() ] )
+                }
+            }
+        );
+
+        ( sizes, ret )
+    }
+
+    #[test]
+    fn failure_in_the_second_chunk()
+    {
+        let mut cmds = vec![ String::from( "add x" ); COMMAND_LIST_CHUNK * 2 + 10 ];
+
+        assert_eq!( run( &cmds ).0, vec![ COMMAND_LIST_CHUNK, COMMAND_LIST_CHUNK, 10 ] );
+        assert_eq!( run( &cmds ).1.unwrap().len(), cmds.len() );
+
+        cmds[ COMMAND_LIST_CHUNK + 3 ] = String::from( "bad" );
+
+        let ( sizes, ret ) = run( &cmds );
+
+        assert_eq!( sizes, vec![ COMMAND_LIST_CHUNK, COMMAND_LIST_CHUNK ] );
+        assert_eq!( ret.unwrap_err().0, COMMAND_LIST_CHUNK + 3 );
+
+        cmds[ 5 ] = String::from( "bad" );
+
+        assert_eq!( run( &cmds ).1.unwrap_err().0, 5 );
+    }
+
+    #[test]
+    fn client_side_error_points_at_the_chunk()
+    {
+        let cmds = vec![ String::from( "add x" ); COMMAND_LIST_CHUNK + 1 ];
+        let mut n = 0;
+
+        let ret = run_chunks( &cmds, |x|
+            {
+                n += 1;
+
+                // cmd_index is left from no command
+                if n == 2 { Err( Ack { code: AckCode::Reconnect, cmd_index: 7, cur_cmd: None, msg_text: None } ) } else { Ok( vec![ ExecOk::default(); x.len() ] ) }
+            }
+        );
+
+        assert_eq!( ret.unwrap_err().0, COMMAND_LIST_CHUNK );
+    }
+
+    /// A `size` of `size` and the chunk at each offset, `None` for no size.
+    fn binary( size: Option<&str>, chunks: &[ &[u8] ] ) -> ( Vec<usize>, BinaryResult )
+    {
+        let mut offsets = Vec::<usize>::new();
+
+        let ret = read_binary( |offset|
+            {
+                let mut flds = fields( &[ ( "type", "image/png" ) ] );
+                flds.extend( size.map( |x| ( String::from( "size" ), String::from( x ) ) ) );
+
+                let bin = chunks.get( offsets.len() ).map( |x| x.to_vec() );
+                offsets.push( offset );
+
+                Ok( ExecOk { flds, bin } )
+            }
+        );
+
+        ( offsets, ret )
+    }
+
+    #[test]
+    fn binary_in_chunks()
+    {
+        let ( offsets, ret ) = binary( Some( "7" ), &[ b"abc", b"def", b"g" ] );
+
+        assert_eq!( offsets, vec![ 0, 3, 6 ] );
+        assert_eq!( ret.unwrap(), Some( ( b"abcdefg".to_vec(), Some( String::from( "image/png" ) ) ) ) );
+
+        assert_eq!( binary( Some( "0" ), &[] ).1.unwrap(), Some( ( Vec::new(), Some( String::from( "image/png" ) ) ) ) );
+    }
+
+    #[test]
+    fn binary_errors()
+    {
+        // no art
+        assert_eq!( binary( None, &[] ).1.unwrap(), None );
+
+        // a chunk short of size, then nothing
+        let ( offsets, ret ) = binary( Some( "7" ), &[ b"abc", b"" ] );
+
+        assert_eq!( offsets, vec![ 0, 3 ] );
+        assert_eq!( ret.unwrap_err().code, AckCode::Binary );
+
+        // past size
+        let ( offsets, ret ) = binary( Some( "5" ), &[ b"abc", b"def" ] );
+
+        assert_eq!( offsets, vec![ 0, 3 ] );
+        assert_eq!( ret.unwrap_err().code, AckCode::Binary );
+
+        assert_eq!( binary( Some( "x" ), &[ b"abc" ] ).1.unwrap_err().code, AckCode::Binary );
+    }
+
+    #[test]
+    fn binary_size_missing_after_the_first_chunk()
+    {
+        let mut n = 0;
+
+        let ret = read_binary( |_|
+            {
+                n += 1;
+
+                let flds = if n == 1 { fields( &[ ( "size", "6" ) ] ) } else { Vec::new() };
+
+                Ok( ExecOk { flds, bin: Some( b"abc".to_vec() ) } )
+            }
+        );
+
+        assert_eq!( n, 2 );
+        assert_eq!( ret.unwrap_err().code, AckCode::Binary );
+    }
+}
@@ -0,0 +1,737 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		commands.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use crate::Mpdsh;
+
+/// What an argument is, for completion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind
+{
+    /// A directory of the database.
+    Dir
+,   /// A file or directory of the database.
+    Path
+,   /// An mpdsh command name.
+    Command
+,   /// A user alias name.
+    Alias
+,   /// A bookmark name.
+    Bookmark
+,   /// A command name of the MPD protocol ( `commands` ).
+    MpdCommand
+,   /// A position in the playlist. Completed from the positions, or from the titles.
+    Pos
+,   /// A position in the playlist, or a pattern of the file names.
+    Song
+,   /// A stored playlist name, or its path under `/.playlists`.
+    Playlist
+,   /// An audio output name.
+    Output
+,   /// A term of find and search, `TAG<OP>VALUE`. Completed from the tag types, then the values of the tag.
+    Filter
+,   /// One of the words.
+    Choice( &'static [ &'static str ] )
+,   /// Anything else. Not completed.
+    Word
+}
+
+const SWITCH        : ArgKind = ArgKind::Choice( &[ "0", "1" ] );
+const SINGLE        : ArgKind = ArgKind::Choice( &[ "0", "1", "oneshot" ] );
+const OUTPUT_STATE  : ArgKind = ArgKind::Choice( &[ "0", "1", "toggle" ] );
+
+#[derive(Debug, Clone, Copy)]
+pub struct Arg
+{
+    pub kind:       ArgKind
+,   pub optional:   bool
+,   /// Takes any number of arguments from here on.
+    pub repeat:     bool
+,   /// Options end here. The words from here on are passed as they are ( `cmd`, `alias` ).
+    pub raw:        bool
+}
+
+const fn req( kind: ArgKind ) -> Arg { Arg { kind, optional: false, repeat: false, raw: false } }
+const fn opt( kind: ArgKind ) -> Arg { Arg { kind, optional: true,  repeat: false, raw: false } }
+const fn many( kind: ArgKind ) -> Arg { Arg { kind, optional: true,  repeat: true,  raw: false } }
+const fn rest( kind: ArgKind ) -> Arg { Arg { kind, optional: true,  repeat: true,  raw: true  } }
+
+/// An option of a command. `-l` / `--long`, or `-o <FILE>` / `--output=<FILE>` with `value`.
+#[derive(Debug, Clone, Copy)]
+pub struct Flag
+{
+    pub short:      char
+,   pub long:       &'static str
+,   /// The name of the value, if the option takes one.
+    pub value:      Option< &'static str >
+}
+
+const fn flag( short: char, long: &'static str ) -> Flag { Flag { short, long, value: None } }
+const fn flag_value( short: char, long: &'static str, value: &'static str ) -> Flag { Flag { short, long, value: Some( value ) } }
+
+/// The arguments of a command line, split by the option schema of the command.
+#[derive(Debug, Default, Clone)]
+pub struct Parsed
+{
+    /// The command name and the positional arguments.
+    pub args:       Vec<String>
+,   /// The options given, by their short name, with their values.
+    pub opts:       Vec< ( char, Option<String> ) >
+}
+
+/// True if `word` is an option. `-` alone and negative numbers ( `del -1` ) are arguments.
+pub fn is_option( word: &str ) -> bool
+{
+    let mut chars = word.chars();
+
+    chars.next() == Some( '-' ) && chars.next().is_some_and( |x| !x.is_ascii_digit() )
+}
+
+/// An entry of the command registry. Dispatch, help and completion all come from `COMMANDS`.
+pub struct Command
+{
+    pub name:       &'static str
+,   pub aliases:    &'static [ &'static str ]
+,   pub args:       &'static [ Arg ]
+,   pub flags:      &'static [ Flag ]
+,   /// The first line is the usage.
+    pub help:       &'static str
+,   pub run:        fn( &mut Mpdsh )
+}
+
+impl Command
+{
+    /// The argument at `index` ( 0 is the first argument after the command name ).
+    /// The arguments from the first repeating one on repeat as a group ( `find TYPE WHAT TYPE WHAT ...` ).
+    pub fn arg( &self, index: usize ) -> Option< &Arg >
+    {
+        if let Some( x ) = self.args.get( index )
+        {
+            return Some( x );
+        }
+
+        let start = self.args.iter().position( |x| x.repeat )?;
+
+        self.args.get( start + ( index - start ) % ( self.args.len() - start ) )
+    }
+
+    /// The usage line, the first line of the help.
+    pub fn usage( &self ) -> &'static str
+    {
+        self.help.trim_start().lines().next().unwrap_or( self.name )
+    }
+
+    /// Splits `words` ( the command name first ) into options and arguments.
+    /// Short options can be joined ( `-la` ), and `--` ends the options. The number of arguments is not checked.
+    pub fn parse( &self, words: &[String] ) -> Result< Parsed, String >
+    {
+        let mut ret         = Parsed::default();
+        let mut only_args   = false;
+        let mut iter        = words.iter();
+
+        ret.args.extend( iter.next().cloned() );
+
+        while let Some( word ) = iter.next()
+        {
+            only_args = only_args || self.arg( ret.args.len() - 1 ).is_some_and( |x| x.raw );
+
+            if only_args || !is_option( word )
+            {
+                ret.args.push( word.clone() );
+                continue;
+            }
+
+            if word == "--"
+            {
+                only_args = true;
+                continue;
+            }
+
+            if let Some( long ) = word.strip_prefix( "--" )
+            {
+                let ( name, value ) = match long.find( '=' )
+                {
+                    Some( x )   => ( &long[ .. x ], Some( String::from( &long[ x + 1 .. ] ) ) )
+                ,   None        => ( long, None )
+                };
+
+                let flag = self.flags.iter().find( |x| x.long == name ).ok_or_else( || format!( "unknown option --{}", name ) )?;
+
+                let value = match ( flag.value, value )
+                {
+                    ( Some(_), Some( x ) )  => Some( x )
+                ,   ( Some( x ), None )     => Some( iter.next().cloned().ok_or_else( || format!( "option --{} needs <{}>", name, x ) )? )
+                ,   ( None, Some(_) )       => return Err( format!( "option --{} takes no value", name ) )
+                ,   ( None, None )          => None
+                };
+
+                ret.opts.push( ( flag.short, value ) );
+                continue;
+            }
+
+            let chars : Vec<char> = word.chars().skip( 1 ).collect();
+
+            for ( i, c ) in chars.iter().enumerate()
+            {
+                let flag = self.flags.iter().find( |x| x.short == *c ).ok_or_else( || format!( "unknown option -{}", c ) )?;
+
+                if let Some( x ) = flag.value
+                {
+                    let value : String = chars[ i + 1 .. ].iter().collect();
+
+                    let value = if value.is_empty()
+                    {
+                        iter.next().cloned().ok_or_else( || format!( "option -{} needs <{}>", c, x ) )?
+                    }
+                    else
+                    {
+                        value
+                    };
+
+                    ret.opts.push( ( *c, Some( value ) ) );
+                    break;
+                }
+
+                ret.opts.push( ( *c, None ) );
+            }
+        }
+
+        Ok( ret )
+    }
+
+    /// True if the last of `words` is an option still waiting for its value ( `tree -d`, `find --sort` ).
+    pub fn needs_value( &self, words: &[String] ) -> bool
+    {
+        let word = match words.last()
+        {
+            Some( x ) if words.len() > 1 && is_option( x ) && x != "--" => x
+        ,   _ => return false
+        };
+
+        if words[ .. words.len() - 1 ].iter().any( |x| x == "--" )
+        {
+            return false;
+        }
+
+        if let Some( long ) = word.strip_prefix( "--" )
+        {
+            return !long.contains( '=' ) && self.flags.iter().any( |x| x.long == long && x.value.is_some() );
+        }
+
+        // the first short option taking a value takes the rest of the word
+        let chars : Vec<char> = word.chars().skip( 1 ).collect();
+
+        match chars.iter().position( |c| self.flags.iter().any( |x| x.short == *c && x.value.is_some() ) )
+        {
+            Some( i )   => i + 1 == chars.len()
+        ,   None        => false
+        }
+    }
+
+    /// Checks the number of arguments of `parsed`.
+    pub fn check( &self, parsed: &Parsed ) -> Result< (), String >
+    {
+        let count   = parsed.args.len().saturating_sub( 1 );
+        let min     = self.args.iter().filter( |x| !x.optional ).count();
+        let max     = if self.args.last().is_some_and( |x| x.repeat ) { usize::MAX } else { self.args.len() };
+
+        if count < min
+        {
+            return Err( String::from( "missing argument" ) );
+        }
+
+        if count > max
+        {
+            return Err( format!( "too many arguments ({})", parsed.args[ max + 1 ] ) );
+        }
+
+        Ok( () )
+    }
+}
+
+const FIND_FLAGS : &[ Flag ] = &[ flag( 'a', "add" ), flag_value( 's', "sort", "TAG" ), flag_value( 'w', "window", "START:END" ) ];
+
+pub static COMMANDS: &[ Command ] = &[
+    Command { name: "cd", aliases: &[], args: &[ opt( ArgKind::Dir ) ], flags: &[], help: HELP_CD, run: |sh| sh.cmd_cd() }
+,   Command { name: "pushd", aliases: &[], args: &[ opt( ArgKind::Dir ) ], flags: &[], help: HELP_PUSHD, run: |sh| sh.cmd_pushd() }
+,   Command { name: "popd", aliases: &[], args: &[], flags: &[], help: HELP_POPD, run: |sh| sh.cmd_popd() }
+,   Command { name: "dirs", aliases: &[], args: &[], flags: &[], help: HELP_DIRS, run: |sh| sh.cmd_dirs() }
+,   Command { name: "mark", aliases: &[], args: &[ opt( ArgKind::Bookmark ), opt( ArgKind::Dir ) ], flags: &[], help: HELP_MARK, run: |sh| sh.cmd_mark() }
+,   Command { name: "unmark", aliases: &[], args: &[ req( ArgKind::Bookmark ), many( ArgKind::Bookmark ) ], flags: &[], help: HELP_UNMARK, run: |sh| sh.cmd_unmark() }
+,   Command { name: "ls", aliases: &[], args: &[ many( ArgKind::Path ) ], flags: &[ flag( 'l', "long" ), flag( 'R', "recursive" ) ], help: HELP_LS, run: |sh| sh.cmd_each_arg( Mpdsh::cmd_ls ) }
+,   Command { name: "tree", aliases: &[], args: &[ opt( ArgKind::Path ) ], flags: &[ flag_value( 'd', "depth", "DEPTH" ) ], help: HELP_TREE, run: |sh| sh.cmd_tree() }
+,   Command { name: "find", aliases: &[], args: &[ req( ArgKind::Filter ), many( ArgKind::Filter ) ], flags: FIND_FLAGS, help: HELP_FIND, run: |sh| sh.cmd_find( false ) }
+,   Command { name: "search", aliases: &[], args: &[ req( ArgKind::Filter ), many( ArgKind::Filter ) ], flags: FIND_FLAGS, help: HELP_SEARCH, run: |sh| sh.cmd_find( true ) }
+
+,   Command { name: "pick", aliases: &[], args: &[ many( ArgKind::Word ) ], flags: &[ flag( 'q', "queue" ), flag( 'p', "playlists" ) ], help: HELP_PICK, run: |sh| sh.cmd_pick() }
+
+,   Command { name: "pl", aliases: &[ "plist" ], args: &[], flags: &[ flag( 'l', "long" ) ], help: HELP_PL, run: |sh| sh.cmd_pl() }
+,   Command { name: "add", aliases: &[ "a" ], args: &[ many( ArgKind::Path ) ], flags: &[], help: HELP_ADD, run: |sh| sh.cmd_each_arg( Mpdsh::cmd_ls ) }
+,   Command { name: "add_top", aliases: &[ "at" ], args: &[ many( ArgKind::Path ) ], flags: &[], help: HELP_ADD_TOP, run: |sh| sh.cmd_each_arg( Mpdsh::cmd_ls ) }
+,   Command { name: "add_uri", aliases: &[], args: &[ req( ArgKind::Word ), opt( ArgKind::Pos ) ], flags: &[], help: HELP_ADD_URI, run: |sh| sh.cmd_with_args( "addid", 2 ) }
+,   Command { name: "load", aliases: &[], args: &[ req( ArgKind::Playlist ), opt( ArgKind::Word ), opt( ArgKind::Pos ) ], flags: &[], help: HELP_LOAD, run: |sh| sh.cmd_playlist( "load", 1, 3 ) }
+,   Command { name: "save", aliases: &[], args: &[ req( ArgKind::Playlist ) ], flags: &[], help: HELP_SAVE, run: |sh| sh.cmd_playlist( "save", 1, 1 ) }
+,   Command { name: "del", aliases: &[], args: &[ req( ArgKind::Song ), many( ArgKind::Song ) ], flags: &[], help: HELP_DEL, run: |sh| sh.cmd_del() }
+,   Command { name: "clr", aliases: &[], args: &[], flags: &[], help: HELP_CLR, run: |sh| sh.cmd_with_args( "clear", 0 ) }
+,   Command { name: "move", aliases: &[], args: &[ req( ArgKind::Pos ), req( ArgKind::Pos ) ], flags: &[], help: HELP_MOVE, run: |sh| sh.cmd_with_args( "move", 2 ) }
+
+,   Command { name: "rm", aliases: &[], args: &[ req( ArgKind::Playlist ) ], flags: &[], help: HELP_RM, run: |sh| sh.cmd_playlist( "rm", 1, 1 ) }
+,   Command { name: "rename", aliases: &[], args: &[ req( ArgKind::Playlist ), req( ArgKind::Playlist ) ], flags: &[], help: HELP_RENAME, run: |sh| sh.cmd_playlist( "rename", 2, 2 ) }
+,   Command { name: "playlistadd", aliases: &[], args: &[ req( ArgKind::Playlist ), req( ArgKind::Path ), many( ArgKind::Path ) ], flags: &[], help: HELP_PLAYLISTADD, run: |sh| sh.cmd_each_arg( Mpdsh::cmd_ls ) }
+,   Command { name: "playlistdelete", aliases: &[], args: &[ req( ArgKind::Playlist ), req( ArgKind::Word ) ], flags: &[], help: HELP_PLAYLISTDELETE, run: |sh| sh.cmd_playlist( "playlistdelete", 1, 2 ) }
+,   Command { name: "playlistmove", aliases: &[], args: &[ req( ArgKind::Playlist ), req( ArgKind::Word ), req( ArgKind::Word ) ], flags: &[], help: HELP_PLAYLISTMOVE, run: |sh| sh.cmd_playlist( "playlistmove", 1, 3 ) }
+,   Command { name: "playlistclear", aliases: &[], args: &[ req( ArgKind::Playlist ) ], flags: &[], help: HELP_PLAYLISTCLEAR, run: |sh| sh.cmd_playlist( "playlistclear", 1, 1 ) }
+
+,   Command { name: "play", aliases: &[ "p" ], args: &[ opt( ArgKind::Pos ) ], flags: &[], help: HELP_PLAY, run: |sh| sh.cmd_with_args( "play", 1 ) }
+,   Command { name: "stop", aliases: &[ "s" ], args: &[], flags: &[], help: HELP_STOP, run: |sh| sh.cmd_with_args( "stop", 0 ) }
+,   Command { name: "pause", aliases: &[ "u" ], args: &[], flags: &[], help: HELP_PAUSE, run: |sh| sh.cmd_with_args( "pause 1", 0 ) }
+,   Command { name: "resume", aliases: &[ "e" ], args: &[], flags: &[], help: HELP_RESUME, run: |sh| sh.cmd_with_args( "pause 0", 0 ) }
+,   Command { name: "prev", aliases: &[ "r" ], args: &[], flags: &[], help: HELP_PREV, run: |sh| sh.cmd_with_args( "previous", 0 ) }
+,   Command { name: "next", aliases: &[ "n" ], args: &[], flags: &[], help: HELP_NEXT, run: |sh| sh.cmd_with_args( "next", 0 ) }
+
+,   Command { name: "random", aliases: &[], args: &[ opt( SWITCH ) ], flags: &[], help: HELP_RANDOM, run: |sh| sh.cmd_switch( "random" ) }
+,   Command { name: "repeat", aliases: &[], args: &[ opt( SWITCH ) ], flags: &[], help: HELP_REPEAT, run: |sh| sh.cmd_switch( "repeat" ) }
+,   Command { name: "single", aliases: &[], args: &[ opt( SINGLE ) ], flags: &[], help: HELP_SINGLE, run: |sh| sh.cmd_switch( "single" ) }
+,   Command { name: "volume", aliases: &[ "v" ], args: &[ opt( ArgKind::Word ) ], flags: &[], help: HELP_VOLUME, run: |sh| sh.cmd_switch( "setvol" ) }
+
+,   Command { name: "output", aliases: &[], args: &[ opt( ArgKind::Output ), opt( OUTPUT_STATE ) ], flags: &[], help: HELP_OUTPUT, run: |sh| sh.cmd_output() }
+
+,   Command { name: "status", aliases: &[ "st" ], args: &[], flags: &[], help: HELP_STATUS, run: |sh| sh.cmd_status() }
+
+,   Command { name: "update", aliases: &[], args: &[ opt( ArgKind::Dir ) ], flags: &[], help: HELP_UPDATE, run: |sh| sh.cmd_update() }
+,   Command { name: "cmd", aliases: &[], args: &[ req( ArgKind::MpdCommand ), rest( ArgKind::Word ) ], flags: &[], help: HELP_CMD, run: |sh| sh.cmd_cmd() }
+,   Command { name: "password", aliases: &[], args: &[ req( ArgKind::Word ) ], flags: &[], help: HELP_PASSWORD, run: |sh| sh.cmd_password() }
+,   Command { name: "cover", aliases: &[], args: &[ req( ArgKind::Path ) ], flags: &[ flag_value( 'o', "output", "OUTFILE" ) ], help: HELP_COVER, run: |sh| sh.cmd_cover() }
+
+,   Command { name: "alias", aliases: &[], args: &[ opt( ArgKind::Alias ), rest( ArgKind::Word ) ], flags: &[], help: HELP_ALIAS, run: |sh| sh.cmd_alias() }
+,   Command { name: "unalias", aliases: &[], args: &[ req( ArgKind::Alias ), many( ArgKind::Alias ) ], flags: &[], help: HELP_UNALIAS, run: |sh| sh.cmd_unalias() }
+
+,   Command { name: "quit", aliases: &[ "q" ], args: &[], flags: &[], help: HELP_QUIT, run: |sh| sh.cmd_quit() }
+,   Command { name: "help", aliases: &[ "h" ], args: &[ opt( ArgKind::Command ) ], flags: &[], help: HELP_HELP, run: |sh| sh.cmd_help() }
+];
+
+/// Looks up a command by its name or alias.
+pub fn find( name: &str ) -> Option< &'static Command >
+{
+    COMMANDS.iter().find( |x| x.name == name || x.aliases.contains( &name ) )
+}
+
+/// The command names, in registry order.
+pub fn names() -> impl Iterator< Item = &'static str >
+{
+    COMMANDS.iter().map( |x| x.name )
+}
+
+/// The command names and their aliases ( `plist`, `a`, ... ), in registry order.
+pub fn names_with_aliases() -> impl Iterator< Item = &'static str >
+{
+    COMMANDS.iter().flat_map( |x| std::iter::once( x.name ).chain( x.aliases.iter().copied() ) )
+}
+
+const HELP_CD : &str = "
+cd [<DIR>|-|@|:<NAME>]
+ - change directory
+ - `-` goes back to the previous directory, `@` to the directory of the current song.
+ - :NAME is the directory bookmarked with mark NAME ( :NAME/... as well, in any path ).
+ - /.artist, /.albumartist, /.genre, /.date and /.composer browse the database by tag,
+   e.g. cd \"/.artist/Miles Davis/Kind of Blue\" ( `/` in a tag is written as %2F ).
+ - /.playlists has the stored playlists as directories.
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_PUSHD : &str = "
+pushd [<DIR>|-|@|:<NAME>]
+ - Changes to DIR, and pushes the current directory on the directory stack.
+ - Without DIR, swaps the current directory and the top of the stack.
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_POPD : &str = "
+popd
+ - Changes to the directory on the top of the directory stack, and pops it.
+";
+
+const HELP_DIRS : &str = "
+dirs
+ - Shows the current directory and the directory stack.
+";
+
+const HELP_MARK : &str = "
+mark [<NAME> [<DIR>]]
+ - Bookmarks DIR ( the current directory ) as NAME, to use as :NAME ( cd :NAME ).
+ - The bookmarks are saved in ~/.config/mpdsh/config.toml.
+ - Without NAME, lists the bookmarks.
+";
+
+const HELP_UNMARK : &str = "
+unmark <NAME> ...
+ - Deletes the bookmark NAME.
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_LS : &str = "
+ls [-l] [-R] [<DIR|FILE> ...]
+ - list file or directory
+ - [-l|--long] more info ( file only )
+ - [-R|--recursive] lists the directories under it as well, with the number of files and the total time.
+   e.g. ls -R Jazz/*.flac
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_TREE : &str = "
+tree [-d <DEPTH>] [<DIR>]
+ - Shows the directories under DIR as a tree, with the number of files and the total time.
+ - [-d|--depth <DEPTH>] shows DEPTH levels only.
+ - Wildcards count the matching files only ( at any depth when in the last part only ), e.g. tree Jazz/*.flac
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_FIND : &str = "
+find [-a] [-s <TAG>] [-w <START:END>] <TERM> ...
+ - Finds songs in the database matching all the TERMs, and lists them as ls -l.
+ - TERM is TAG==VALUE ( or = ), TAG!=VALUE, TAG~VALUE ( contains ), TAG!~VALUE ( does not contain ),
+   TAG=~REGEX, TAG>=VALUE, TAG<=VALUE, TAG>VALUE or TAG<VALUE.
+   TAG is artist, album, title, date, genre, ... or any, file and base ( the directory, `base==DIR` only ).
+ - A word without an operator is any~WORD. `TAG VALUE` is TAG==VALUE.
+ - e.g. find artist~coltrane date>=1960 genre!=live
+ - [-s|--sort <TAG>] sorts by TAG ( -TAG descending ).
+ - [-w|--window <START:END>] lists only those of the results.
+ - [-a|--add] adds the songs to the playlist ( findadd ).
+ - e.g. add $(find artist \"Miles Davis\")
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_SEARCH : &str = "
+search [-a] [-s <TAG>] [-w <START:END>] <TERM> ...
+ - Like find, ignoring case. -a adds with searchadd.
+ - e.g. search artist~COLTRANE
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_PICK : &str = "
+pick [-q|-p] [<QUERY> ...]
+ - Picks files of the database with a fuzzy finder, and prints them.
+ - [-q|--queue] songs of the playlist ( prints the positions )
+ - [-p|--playlists] stored playlists
+ - Feed the selection to other commands, e.g. add $(pick), del $(pick -q), play $(pick -q), load $(pick -p)
+ - Uses fzf if installed ( or `picker` in config.toml ). Otherwise, type words to filter, numbers ( 1 3 5-7, * for all ) to pick.
+";
+
+const HELP_PL : &str = "
+pl [-l]
+ - show playlist
+ - [-l|--long] more info
+ - FLG `=>` The current song stopped on or playing.
+ - FLG `.`  The next song to be played.
+";
+
+const HELP_ADD : &str = "
+add [<FILE|DIR>]
+ - Adds the file to the playlist (directories add recursively).
+ - If no file is specified, all files under the current directory are targeted.
+ - Wildcards match across directories, e.g. add Jazz/**/*.flac, add */Disc{1,2}
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_ADD_TOP : &str = "
+add_top [<FILE|DIR>]
+ - Adds the file to the playlist top (directories add recursively).
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_ADD_URI : &str = "
+add_uri <URI> [<POSITION>]
+ - Adds the file to the playlist.
+ - URL of Internet radio, etc.
+";
+
+const HELP_LOAD : &str = "
+load <NAME> [<START:END>] [<POSITION>]
+ - Adds the stored playlist NAME to the playlist.
+ - <START:END> adds only those songs of it, <POSITION> inserts them there.
+ - NAME may also be given as a path, like /.playlists/NAME.
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_SAVE : &str = "
+save <NAME>
+ - Saves the playlist as the stored playlist NAME.
+";
+
+const HELP_RM : &str = "
+rm <NAME>
+ - Deletes the stored playlist NAME.
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_RENAME : &str = "
+rename <NAME> <NEW_NAME>
+ - Renames the stored playlist NAME.
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_PLAYLISTADD : &str = "
+playlistadd <NAME> <FILE|DIR> ...
+ - Adds the files to the stored playlist NAME ( creates it if missing ).
+ - Works like add, including wildcards and the virtual directories.
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_PLAYLISTDELETE : &str = "
+playlistdelete <NAME> <POS>|<START:END>
+ - Deletes songs from the stored playlist NAME.
+ - The positions are those of ls /.playlists/NAME, starting from 0.
+";
+
+const HELP_PLAYLISTMOVE : &str = "
+playlistmove <NAME> <FROM> <TO>
+ - Moves the song at FROM to TO in the stored playlist NAME.
+";
+
+const HELP_PLAYLISTCLEAR : &str = "
+playlistclear <NAME>
+ - Deletes all songs from the stored playlist NAME.
+";
+
+const HELP_DEL : &str = "
+del <POS>|<-N>|<START:END>|<PATTERN> ...
+ - Deletes songs from the playlist.
+ - <-N> counts from the end ( -1 is the last song ).
+ - <PATTERN> deletes every song whose file name matches it ( wildcards `*` `?` `[...]` `{a,b}` ).
+ - A <PATTERN> with `/` matches the paths, and the songs in a matching directory, e.g. del Jazz/**/*live*
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_CLR : &str = "
+clr
+ - Deletes all songs from the playlist.
+";
+
+const HELP_MOVE : &str = "
+move <POS>|<START:END> <TOPOS>
+ - Moves the song in the playlist.
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_PLAY : &str = "
+play [<POS>]
+ - Begins playing the playlist.
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_STOP : &str = "
+stop
+ - Stops playing.
+";
+
+const HELP_PAUSE : &str = "
+pause
+ - Toggles pause playing.
+";
+
+const HELP_RESUME : &str = "
+resume
+ - Toggles resumes playing.
+";
+
+const HELP_PREV : &str = "
+prev
+ - Plays previous song in the playlist.
+";
+
+const HELP_NEXT : &str = "
+next
+ - Plays next song in the playlist.
+";
+
+const HELP_RANDOM : &str = "
+random [<STATE>]
+ - Sets random state to STATE, STATE should be 0 or 1.
+ - Or display the current value.
+";
+
+const HELP_REPEAT : &str = "
+repeat [<STATE>]
+ - Sets repeat state to STATE, STATE should be 0 or 1.
+ - Or display the current value.
+";
+
+const HELP_SINGLE : &str = "
+single <STATE>
+ - Sets single state to STATE, STATE should be 0, 1 or `oneshot`
+ - When single is activated, playback is stopped after current song, or song is repeated if the ‘repeat’ mode is enabled.
+ - Or display the current value.
+";
+
+const HELP_VOLUME : &str = "
+volume <VOL>
+ - Sets volume to VOL, the range of volume is 0-100.
+ - Or display the current value.
+";
+
+const HELP_OUTPUT : &str = "
+output [<NAME|ID> [<STATE>]]
+ - Sets the audio output to STATE, STATE should be 0, 1 or `toggle`.
+ - Or display the outputs.
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_STATUS : &str = "
+status
+ - Reports the current status of the player and the volume level.
+";
+
+const HELP_UPDATE : &str = "
+update [<DIR>]
+ - Updates the music database on MPD
+ - Drops the cached directory listings ( completion, wildcards, ls ). They are dropped again when MPD reports the update.
+";
+
+const HELP_CMD : &str = "
+cmd <MPDCOMMAND> [<MPDCOMMAND_ARG> ...]
+ - Exec MPD Protocol command (see:https://www.musicpd.org/doc/html/protocol.html)
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_PASSWORD : &str = "
+password <PASSWORD>
+ - Sends the password to MPD and shows which commands it unlocked.
+";
+
+const HELP_COVER : &str = "
+cover [-o <OUTFILE>] <FILE>
+ - Saves the cover art of FILE to a local file ( -o, --output, default: cover.<EXT> ).
+ - Uses the cover file in the directory (albumart), or the picture embedded in FILE (readpicture).
+ - You can use the <TAB> key for completion.
+";
+
+const HELP_ALIAS : &str = "
+alias [<NAME> [=] <COMMANDS>]
+ - Defines NAME to run COMMANDS ( separated by `;` ). Saved to ~/.config/mpdsh/config.toml.
+ - $1 .. $9 are replaced by the arguments, $@ by all of them. Otherwise the arguments are appended.
+ - e.g. alias jz = \"cd /Jazz; add; play\"
+ - Several words are kept as they were quoted, e.g. alias k = cd \"Kind of Blue\"
+ - A built-in command can not be an alias name, in config.toml neither.
+ - Aliases defined in ~/.mpdshrc are not saved.
+ - Without COMMANDS, shows the alias. Without NAME, lists all aliases.
+";
+
+const HELP_UNALIAS : &str = "
+unalias <NAME> ...
+ - Removes the alias.
+";
+
+const HELP_HELP : &str = "
+help help help ... help!
+ - I want you to help me.
+";
+
+const HELP_QUIT : &str = "
+quit
+ - Quit this program.
+";
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn words( line: &str ) -> Vec<String>
+    {
+        line.split_whitespace().map( String::from ).collect()
+    }
+
+    fn parse( line: &str ) -> Result< Parsed, String >
+    {
+        let w = words( line );
+
+        find( &w[0] ).unwrap().parse( &w )
+    }
+
+    #[test]
+    fn bundled_short_options()
+    {
+        let p = parse( "ls -lR Jazz" ).unwrap();
+
+        assert_eq!( p.args, words( "ls Jazz" ) );
+        assert_eq!( p.opts, vec![ ( 'l', None ), ( 'R', None ) ] );
+    }
+
+    #[test]
+    fn option_values()
+    {
+        let p = parse( "find -s Date --window=0:10 artist miles" ).unwrap();
+
+        assert_eq!( p.args, words( "find artist miles" ) );
+        assert_eq!( p.opts, vec![ ( 's', Some( String::from( "Date" ) ) ), ( 'w', Some( String::from( "0:10" ) ) ) ] );
+
+        assert_eq!( parse( "tree -d2" ).unwrap().opts, vec![ ( 'd', Some( String::from( "2" ) ) ) ] );
+        assert_eq!( parse( "tree --depth 3 Jazz" ).unwrap().opts, vec![ ( 'd', Some( String::from( "3" ) ) ) ] );
+        assert_eq!( parse( "find -as Date x" ).unwrap().opts, vec![ ( 'a', None ), ( 's', Some( String::from( "Date" ) ) ) ] );
+    }
+
+    #[test]
+    fn end_of_options_and_negative_numbers()
+    {
+        let p = parse( "ls -l -- -live-" ).unwrap();
+
+        assert_eq!( p.args, words( "ls -live-" ) );
+        assert_eq!( p.opts, vec![ ( 'l', None ) ] );
+
+        assert_eq!( parse( "del -1" ).unwrap().args, words( "del -1" ) );
+        assert!( parse( "del -1" ).unwrap().opts.is_empty() );
+
+        // the rest of `cmd` goes to MPD as it is
+        assert_eq!( parse( "cmd list -x" ).unwrap().args, words( "cmd list -x" ) );
+    }
+
+    #[test]
+    fn option_errors()
+    {
+        assert!( parse( "ls -x" ).is_err() );
+        assert!( parse( "ls --nope" ).is_err() );
+        assert!( parse( "tree -d" ).is_err() );
+        assert!( parse( "ls --long=1" ).is_err() );
+    }
+
+    #[test]
+    fn argument_count()
+    {
+        let check = |line: &str| { let w = words( line ); let c = find( &w[0] ).unwrap(); c.check( &c.parse( &w ).unwrap() ) };
+
+        assert!( check( "del" ).is_err() );
+        assert!( check( "del 1 2 3" ).is_ok() );
+        assert!( check( "tree a b" ).is_err() );
+        assert!( check( "tree" ).is_ok() );
+    }
+
+    #[test]
+    fn option_waiting_for_value()
+    {
+        let tree = find( "tree" ).unwrap();
+        let find = find( "find" ).unwrap();
+
+        assert!( tree.needs_value( &words( "tree -d" ) ) );
+        assert!( tree.needs_value( &words( "tree --depth" ) ) );
+        assert!( !tree.needs_value( &words( "tree -d2" ) ) );
+        assert!( !tree.needs_value( &words( "tree --depth=2" ) ) );
+        assert!( !tree.needs_value( &words( "tree -- -d" ) ) );
+        assert!( find.needs_value( &words( "find -as" ) ) );
+        assert!( !find.needs_value( &words( "find -a" ) ) );
+        assert!( !find.needs_value( &words( "find" ) ) );
+    }
+
+    #[test]
+    fn names_and_aliases()
+    {
+        assert_eq!( find( "a" ).map( |x| x.name ), Some( "add" ) );
+        assert!( find( "nope" ).is_none() );
+        assert!( is_option( "-l" ) && is_option( "--long" ) );
+        assert!( !is_option( "-" ) && !is_option( "-1" ) && !is_option( "l" ) );
+    }
+}
@@ -0,0 +1,135 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		config.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::cache::Mode;
+
+/// A server profile of `[profiles.<NAME>]`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile
+{
+    pub host:       Option<String>
+,   pub port:       Option<u16>
+,   /// Unix socket path ( /path or @abstract ). Used instead of host and port.
+    pub socket:     Option<String>
+,   pub password:   Option<String>
+,   /// The directory to cd at startup.
+    pub dir:        Option<String>
+}
+
+/// `~/.config/mpdsh/config.toml`
+#[derive(Debug, Default, Deserialize)]
+pub struct Config
+{
+    #[serde(default)]
+    pub profiles:   BTreeMap< String, Profile >
+,   /// `[aliases]` name = "commands"
+    #[serde(default)]
+    pub aliases:    BTreeMap< String, String >
+,   /// `[bookmarks]` name = "/directory", set by `mark`.
+    #[serde(default)]
+    pub bookmarks:  BTreeMap< String, String >
+,   /// The command `pick` runs ( default: `fzf -m`, or the built-in picker ). `builtin` for the built-in one.
+    pub picker:     Option< String >
+,   /// The number of directories the prompt shows. Deeper paths are shortened to `.../<the last ones>`.
+    pub prompt_depth: Option< usize >
+,   /// The directory cache: `off`, `memory` ( default ) or `disk` ( kept for each server ).
+    /// A script has no idle listener, it checks the `db_update` of `stats` before each listing instead.
+    pub cache:      Option< Mode >
+}
+
+impl Config
+{
+    /// `$XDG_CONFIG_HOME/mpdsh/config.toml`, or `~/.config/mpdsh/config.toml`.
+    pub fn path() -> Option< PathBuf >
+    {
+        std::env::var_os( "XDG_CONFIG_HOME" ).filter( |x| !x.is_empty() ).map( PathBuf::from )
+            .or_else( || dirs::home_dir().map( |x| x.join( ".config" ) ) )
+            .map( |x| x.join( "mpdsh" ).join( "config.toml" ) )
+    }
+
+    /// Loads the config file. A missing file is an empty config.
+    pub fn load() -> Result< Config, String >
+    {
+        let path = match Self::path()
+        {
+            Some( x ) => x
+        ,   None    => return Ok( Config::default() )
+        };
+
+        match std::fs::read_to_string( &path )
+        {
+            Ok( x ) => toml_edit::de::from_str( &x ).map_err( |e| format!( "{}: {}", path.display(), e ) )
+        ,   Err( x ) if x.kind() == std::io::ErrorKind::NotFound => Ok( Config::default() )
+        ,   Err( x ) => Err( format!( "{}: {}", path.display(), x ) )
+        }
+    }
+
+    pub fn profile( &self, name: &str ) -> Option< &Profile >
+    {
+        self.profiles.get( name )
+    }
+
+    /// Sets ( or removes with None ) `aliases.<name>` in the config file.
+    pub fn save_alias( name: &str, value: Option< &str > ) -> Result< (), String >
+    {
+        Self::save_entry( "aliases", name, value )
+    }
+
+    /// Sets ( or removes with None ) `bookmarks.<name>` in the config file.
+    pub fn save_bookmark( name: &str, value: Option< &str > ) -> Result< (), String >
+    {
+        Self::save_entry( "bookmarks", name, value )
+    }
+
+    /// Sets ( or removes with None ) `<table>.<name>` in the config file. Other contents and comments are kept.
+    fn save_entry( table: &str, name: &str, value: Option< &str > ) -> Result< (), String >
+    {
+        let path = Self::path().ok_or_else( || String::from( "no home directory" ) )?;
+
+        let text = match std::fs::read_to_string( &path )
+        {
+            Ok( x ) => x
+        ,   Err( x ) if x.kind() == std::io::ErrorKind::NotFound => String::new()
+        ,   Err( x ) => return Err( format!( "{}: {}", path.display(), x ) )
+        };
+
+        let mut doc = text.parse::< toml_edit::Document >().map_err( |e| format!( "{}: {}", path.display(), e ) )?;
+
+        if !doc.contains_table( table )
+        {
+            doc[ table ] = toml_edit::table();
+        }
+
+        let entries = doc[ table ].as_table_mut().ok_or_else( || format!( "{}: {} is not a table", path.display(), table ) )?;
+
+        match value
+        {
+            Some( x )   => { entries[ name ] = toml_edit::value( x ); }
+        ,   None        => { entries.remove( name ); }
+        }
+
+        if let Some( x ) = path.parent()
+        {
+            std::fs::create_dir_all( x ).map_err( |e| format!( "{}: {}", x.display(), e ) )?;
+        }
+
+        std::fs::write( &path, doc.to_string() ).map_err( |e| format!( "{}: {}", path.display(), e ) )
+    }
+}
+
+/// `~/.mpdshrc`
+pub fn rc_path() -> Option< PathBuf >
+{
+    dirs::home_dir().map( |x| x.join( ".mpdshrc" ) )
+}
@@ -0,0 +1,209 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		error.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::fmt;
+
+/// The error code of an `ACK [code@index]` response ( see `src/protocol/Ack.hxx` of MPD ),
+/// or a client side error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckCode
+{
+    NotList
+,   Arg
+,   Password
+,   Permission
+,   Unknown
+,   NoExist
+,   PlaylistMax
+,   System
+,   PlaylistLoad
+,   UpdateAlready
+,   PlayerSync
+,   Exist
+,   /// exec_command() could not talk to the worker thread. ( -2 )
+    Worker
+,   /// The connection was lost while the command was in flight. The command was not completed. ( -3 )
+    Reconnect
+,   /// A binary transfer ( albumart, readpicture ) ended with a wrong or missing size. ( -4 )
+    Binary
+,   /// A code this client does not know.
+    Other( i32 )
+}
+
+impl AckCode
+{
+    pub fn from_i32( code: i32 ) -> AckCode
+    {
+        match code
+        {
+            1   => AckCode::NotList
+        ,   2   => AckCode::Arg
+        ,   3   => AckCode::Password
+        ,   4   => AckCode::Permission
+        ,   5   => AckCode::Unknown
+        ,   50  => AckCode::NoExist
+        ,   51  => AckCode::PlaylistMax
+        ,   52  => AckCode::System
+        ,   53  => AckCode::PlaylistLoad
+        ,   54  => AckCode::UpdateAlready
+        ,   55  => AckCode::PlayerSync
+        ,   56  => AckCode::Exist
+        ,   -2  => AckCode::Worker
+        ,   -3  => AckCode::Reconnect
+        ,   -4  => AckCode::Binary
+        ,   x   => AckCode::Other( x )
+        }
+    }
+
+    /// The numeric code. Client side errors are negative.
+    pub fn as_i32( &self ) -> i32
+    {
+        match self
+        {
+            AckCode::NotList        => 1
+        ,   AckCode::Arg            => 2
+        ,   AckCode::Password       => 3
+        ,   AckCode::Permission     => 4
+        ,   AckCode::Unknown        => 5
+        ,   AckCode::NoExist        => 50
+        ,   AckCode::PlaylistMax    => 51
+        ,   AckCode::System         => 52
+        ,   AckCode::PlaylistLoad   => 53
+        ,   AckCode::UpdateAlready  => 54
+        ,   AckCode::PlayerSync     => 55
+        ,   AckCode::Exist          => 56
+        ,   AckCode::Worker         => -2
+        ,   AckCode::Reconnect      => -3
+        ,   AckCode::Binary         => -4
+        ,   AckCode::Other( x )     => *x
+        }
+    }
+
+    /// True for errors raised by this client, not by the server.
+    pub fn is_client_side( &self ) -> bool
+    {
+        matches!( self, AckCode::Worker | AckCode::Reconnect | AckCode::Binary )
+    }
+
+    /// The name used by MPD ( `ACK_ERROR_` is omitted ).
+    pub fn name( &self ) -> &'static str
+    {
+        match self
+        {
+            AckCode::NotList        => "NOT_LIST"
+        ,   AckCode::Arg            => "ARG"
+        ,   AckCode::Password       => "PASSWORD"
+        ,   AckCode::Permission     => "PERMISSION"
+        ,   AckCode::Unknown        => "UNKNOWN"
+        ,   AckCode::NoExist        => "NO_EXIST"
+        ,   AckCode::PlaylistMax    => "PLAYLIST_MAX"
+        ,   AckCode::System         => "SYSTEM"
+        ,   AckCode::PlaylistLoad   => "PLAYLIST_LOAD"
+        ,   AckCode::UpdateAlready  => "UPDATE_ALREADY"
+        ,   AckCode::PlayerSync     => "PLAYER_SYNC"
+        ,   AckCode::Exist          => "EXIST"
+        ,   AckCode::Worker         => "WORKER"
+        ,   AckCode::Reconnect      => "RECONNECT"
+        ,   AckCode::Binary         => "BINARY"
+        ,   AckCode::Other(_)       => "OTHER"
+        }
+    }
+
+    /// A human readable explanation.
+    pub fn description( &self ) -> &'static str
+    {
+        match self
+        {
+            AckCode::NotList        => "Command list error."
+        ,   AckCode::Arg            => "Bad argument."
+        ,   AckCode::Password       => "Incorrect password."
+        ,   AckCode::Permission     => "Permission denied."
+        ,   AckCode::Unknown        => "Unknown command."
+        ,   AckCode::NoExist        => "No such file, directory, playlist or song."
+        ,   AckCode::PlaylistMax    => "The playlist is full."
+        ,   AckCode::System         => "System error on the server."
+        ,   AckCode::PlaylistLoad   => "The playlist could not be loaded."
+        ,   AckCode::UpdateAlready  => "A database update is already running."
+        ,   AckCode::PlayerSync     => "The player state could not be synchronized."
+        ,   AckCode::Exist          => "Already exists."
+        ,   AckCode::Worker         => "Lost contact with the connection thread."
+        ,   AckCode::Reconnect      => "The connection was lost. The command was not completed."
+        ,   AckCode::Binary         => "The binary transfer failed."
+        ,   AckCode::Other(_)       => "Unexpected error."
+        }
+    }
+
+    /// A suggested fix, if there is one.
+    pub fn hint( &self ) -> Option< &'static str >
+    {
+        match self
+        {
+            AckCode::NotList        => Some( "Do not nest command_list_begin / command_list_end with `cmd`." )
+        ,   AckCode::Arg            => Some( "Check the arguments with `help`." )
+        ,   AckCode::Password       => Some( "Run `password <PASSWORD>` with the correct password." )
+        ,   AckCode::Permission     => Some( "Run `password <PASSWORD>` to unlock the command." )
+        ,   AckCode::Unknown        => Some( "Run `cmd commands` to see the commands the server supports." )
+        ,   AckCode::NoExist        => Some( "Check the name with `ls`, or the position with `pl`." )
+        ,   AckCode::PlaylistMax    => Some( "Remove songs with `del`, or raise max_playlist_length in mpd.conf." )
+        ,   AckCode::System         => Some( "Check the MPD log on the server." )
+        ,   AckCode::PlaylistLoad   => Some( "Check that the playlist file is readable on the server." )
+        ,   AckCode::UpdateAlready  => Some( "Wait until the running update finishes." )
+        ,   AckCode::PlayerSync     => Some( "Run the command again." )
+        ,   AckCode::Exist          => Some( "Use another name, or remove the existing one first." )
+        ,   AckCode::Worker         => Some( "Restart mpdsh." )
+        ,   AckCode::Reconnect      => Some( "Run the command again." )
+        ,   AckCode::Binary         => Some( "Run the command again." )
+        ,   AckCode::Other(_)       => None
+        }
+    }
+}
+
+impl fmt::Display for AckCode
+{
+    fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+    {
+        write!( f, "{}:{}", self.name(), self.as_i32() )
+    }
+}
+
+/// An `ACK [code@index] {command} message` response, or a client side error.
+#[derive(Debug, Clone)]
+pub struct Ack
+{
+    pub code:       AckCode
+,   pub cmd_index:  usize
+,   pub cur_cmd:    Option<String>
+,   pub msg_text:   Option<String>
+}
+
+impl Ack
+{
+    pub fn new( code: AckCode, msg_text: Option<String> ) -> Ack
+    {
+        Ack { code, cmd_index: 0, cur_cmd: None, msg_text }
+    }
+}
+
+impl fmt::Display for Ack
+{
+    fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+    {
+        write!( f, "{}", self.code.description() )?;
+
+        match ( &self.cur_cmd, &self.msg_text )
+        {
+            ( Some( c ), Some( x ) ) if !c.is_empty()   => write!( f, " ({{{}}} {})", c, x )?
+        ,   ( _, Some( x ) )                            => write!( f, " ({})", x )?
+        ,   _                                           => {}
+        }
+
+        write!( f, " [{}]", self.code )
+    }
+}
+
+impl std::error::Error for Ack {}
@@ -0,0 +1,253 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		filter.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::cmp::Ordering;
+
+use mpdsh::Song;
+
+/// The operators of a term, longest first. `~` is `contains`, `=~` a regular expression.
+const OPS : &[ &str ] = &[ "==", "!=", "!~", "=~", ">=", "<=", "=", "~", ">", "<" ];
+
+/// The names of the tags as MPD writes them, for the expressions.
+const TAGS : &[ &str ] = &[
+    "Artist", "ArtistSort", "Album", "AlbumSort", "AlbumArtist", "AlbumArtistSort", "Title", "TitleSort"
+,   "Track", "Name", "Genre", "Mood", "Date", "OriginalDate", "Composer", "ComposerSort", "Performer"
+,   "Conductor", "Work", "Ensemble", "Movement", "MovementNumber", "Location", "Grouping", "Comment"
+,   "Disc", "Label", "AudioFormat"
+];
+
+/// A comparison MPD can not do ( `date>=1960` ). The songs are checked here.
+#[derive(Debug, Clone)]
+pub struct Check
+{
+    pub tag:    String
+,   pub op:     &'static str
+,   pub value:  String
+}
+
+/// Readable terms translated to an MPD filter expression.
+#[derive(Debug, Clone)]
+pub struct Query
+{
+    pub expr:   String
+,   /// The comparisons to check on the songs found by `expr`.
+    pub checks: Vec< Check >
+}
+
+/// A value of a filter expression, in double quotes.
+pub fn value( x: &str ) -> String
+{
+    format!( "\"{}\"", x.replace( '\\', "\\\\" ).replace( '"', "\\\"" ) )
+}
+
+/// The tag name as MPD writes it. `any`, `file` and `base` in lower case.
+fn tag_name( x: &str ) -> String
+{
+    match TAGS.iter().find( |t| t.eq_ignore_ascii_case( x ) )
+    {
+        Some( t )   => String::from( *t )
+    ,   None        => x.to_lowercase()
+    }
+}
+
+/// Splits `artist~coltrane` into the tag, the operator and the value. None if `word` has no operator.
+pub fn split_term( word: &str ) -> Option< ( &str, &'static str, &str ) >
+{
+    let at = word.find( |c: char| "=!~<>".contains( c ) )?;
+
+    let tag = &word[ .. at ];
+
+    if tag.is_empty() || !tag.chars().all( |c| c.is_ascii_alphanumeric() || c == '_' || c == '-' )
+    {
+        return None;
+    }
+
+    let op = OPS.iter().find( |x| word[ at .. ].starts_with( **x ) )?;
+
+    Some( ( tag, op, &word[ at + op.len() .. ] ) )
+}
+
+/// Translates the terms into a filter expression. The terms are joined with AND.
+///  - `TAG==VALUE` ( or `=` ), `TAG!=VALUE`, `TAG~VALUE` ( contains ), `TAG!~VALUE`, `TAG=~REGEX`
+///  - `TAG>=VALUE`, `TAG<=VALUE`, `TAG>VALUE`, `TAG<VALUE`, numbers or text ( dates ) compared here
+///  - `TAG VALUE`, if TAG is in `tags`, as `TAG==VALUE` ( the old `find TYPE WHAT` )
+///  - `base==DIR` ( the songs under DIR ), no other operator for `base`
+///  - `WORD` as `any~WORD`, and `(...)` as it is
+pub fn parse( words: &[ String ], tags: &[ String ] ) -> Result< Query, String >
+{
+    let is_tag = |x: &str| [ "any", "file", "base" ].iter().any( |t| t.eq_ignore_ascii_case( x ) ) || tags.iter().any( |t| t.eq_ignore_ascii_case( x ) );
+
+    let mut terms   = Vec::< String >::new();
+    let mut checks  = Vec::< Check >::new();
+    let mut iter    = words.iter().peekable();
+
+    while let Some( word ) = iter.next()
+    {
+        if word.starts_with( '(' )
+        {
+            terms.push( word.clone() );
+            continue;
+        }
+
+        let ( tag, op, val ) = match split_term( word )
+        {
+            Some( x )                                       => x
+        ,   None if is_tag( word ) && iter.peek().is_some() => ( word.as_str(), "==", iter.next().unwrap().as_str() )
+        ,   None                                            => ( "any", "~", word.as_str() )
+        };
+
+        let tag = tag_name( tag );
+
+        terms.push(
+            match op
+            {
+                // MPD takes `base` with a value only, the songs under that directory
+                "==" | "=" if tag == "base"         => format!( "(base {})", value( val ) )
+            ,   _ if tag == "base"                  => return Err( format!( "base takes == only... {}", word ) )
+            ,   "==" | "="                          => format!( "({} == {})", tag, value( val ) )
+            ,   "!="                                => format!( "({} != {})", tag, value( val ) )
+            ,   "~"                                 => format!( "({} contains {})", tag, value( val ) )
+            ,   "!~"                                => format!( "(!({} contains {}))", tag, value( val ) )
+            ,   "=~"                                => format!( "({} =~ {})", tag, value( val ) )
+            ,   _ if tag == "any" || tag == "file"  => return Err( format!( "{} can not be compared... {}", tag, word ) )
+            ,   _                                   =>
+                {
+                    checks.push( Check { tag: tag.clone(), op, value: String::from( val ) } );
+
+                    format!( "({} != \"\")", tag )
+                }
+            }
+        );
+    }
+
+    let expr = match terms.len()
+    {
+        0   => return Err( String::from( "no filter" ) )
+    ,   1   => terms.remove( 0 )
+    ,   _   => format!( "({})", terms.join( " AND " ) )
+    };
+
+    Ok( Query { expr, checks } )
+}
+
+impl Check
+{
+    /// Numbers are compared as numbers, anything else as text, as long as the value.
+    /// So `date<=1960` takes `1960-05-01` as 1960.
+    pub fn matches( &self, song: &Song ) -> bool
+    {
+        let x = match song.tag( &self.tag )
+        {
+            Some( x )   => x
+        ,   None        => return false
+        };
+
+        let ord = match ( x.trim().parse::<f64>(), self.value.trim().parse::<f64>() )
+        {
+            ( Ok( a ), Ok( b ) )    => a.partial_cmp( &b ).unwrap_or( Ordering::Equal )
+        ,   _                       => x.get( .. self.value.len() ).unwrap_or( x ).cmp( self.value.as_str() )
+        };
+
+        match self.op
+        {
+            ">="    => ord != Ordering::Less
+        ,   "<="    => ord != Ordering::Greater
+        ,   ">"     => ord == Ordering::Greater
+        ,   _       => ord == Ordering::Less
+        }
+    }
+}
+
+impl Query
+{
+    pub fn matches( &self, song: &Song ) -> bool
+    {
+        self.checks.iter().all( |x| x.matches( song ) )
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn words( line: &str ) -> Vec<String>
+    {
+        line.split_whitespace().map( String::from ).collect()
+    }
+
+    fn expr( line: &str ) -> String
+    {
+        parse( &words( line ), &words( "Artist Album Date" ) ).unwrap().expr
+    }
+
+    fn song( date: &str ) -> Song
+    {
+        Song::from_fields( vec![ ( String::from( "file" ), String::from( "a.flac" ) ), ( String::from( "Date" ), String::from( date ) ) ] ).unwrap()
+    }
+
+    #[test]
+    fn terms()
+    {
+        assert_eq!( split_term( "artist~coltrane" ), Some( ( "artist", "~", "coltrane" ) ) );
+        assert_eq!( split_term( "date>=1960" ), Some( ( "date", ">=", "1960" ) ) );
+        assert_eq!( split_term( "title==a=b" ), Some( ( "title", "==", "a=b" ) ) );
+        assert_eq!( split_term( "album!~live" ), Some( ( "album", "!~", "live" ) ) );
+        assert_eq!( split_term( "coltrane" ), None );
+        assert_eq!( split_term( "=x" ), None );
+        assert_eq!( split_term( "a b~c" ), None );
+    }
+
+    #[test]
+    fn expressions()
+    {
+        assert_eq!( expr( "artist~coltrane" ), "(Artist contains \"coltrane\")" );
+        assert_eq!( expr( "artist!~coltrane" ), "(!(Artist contains \"coltrane\"))" );
+        assert_eq!( expr( "album=blue" ), "(Album == \"blue\")" );
+        assert_eq!( expr( "album==blue" ), "(Album == \"blue\")" );
+        assert_eq!( expr( "title=~^So" ), "(Title =~ \"^So\")" );
+        assert_eq!( expr( "file!=a\"b" ), "(file != \"a\\\"b\")" );
+        assert_eq!( expr( "base==Jazz/Sub" ), "(base \"Jazz/Sub\")" );
+        assert_eq!( expr( "BASE Jazz" ), "(base \"Jazz\")" );
+        assert_eq!( expr( "miles" ), "(any contains \"miles\")" );
+        assert_eq!( expr( "artist miles" ), "(Artist == \"miles\")" );
+        assert_eq!( parse( &[ String::from( "(Artist == \"x\")" ) ], &[] ).unwrap().expr, "(Artist == \"x\")" );
+        assert_eq!( expr( "artist~miles blue" ), "((Artist contains \"miles\") AND (any contains \"blue\"))" );
+    }
+
+    #[test]
+    fn comparisons()
+    {
+        let q = parse( &words( "date>=1959 date<1961" ), &[] ).unwrap();
+
+        assert_eq!( q.expr, "((Date != \"\") AND (Date != \"\"))" );
+        assert_eq!( q.checks.len(), 2 );
+
+        assert!( q.matches( &song( "1959-08-17" ) ) );
+        assert!( q.matches( &song( "1960" ) ) );
+        assert!( !q.matches( &song( "1961-02" ) ) );
+        assert!( !q.matches( &song( "1958" ) ) );
+        assert!( !q.matches( &Song::from_fields( vec![ ( String::from( "file" ), String::from( "a.flac" ) ) ] ).unwrap() ) );
+
+        // numbers as numbers
+        let q = parse( &words( "date>9" ), &[] ).unwrap();
+
+        assert!( q.matches( &song( "10" ) ) );
+    }
+
+    #[test]
+    fn errors()
+    {
+        assert!( parse( &[], &[] ).is_err() );
+        assert!( parse( &words( "any>=1" ), &[] ).is_err() );
+        assert!( parse( &words( "file<a" ), &[] ).is_err() );
+        assert!( parse( &words( "base~Jazz" ), &[] ).is_err() );
+        assert!( parse( &words( "base!=Jazz" ), &[] ).is_err() );
+        assert!( parse( &words( "base>=Jazz" ), &[] ).is_err() );
+    }
+}
@@ -0,0 +1,431 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		glob.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+/// True if `x` has a wildcard ( `*` `?` `[...]` ) or a brace ( `{a,b}` ).
+/// A name may have them too ( `Album [Deluxe]` ), so the callers look for the entry as it is first.
+pub fn has_magic( x: &str ) -> bool
+{
+    x.contains( |c: char| "*?[{".contains( c ) )
+}
+
+/// `x` as a pattern matching only itself, `Album [Deluxe]` as `Album \[Deluxe\]`.
+pub fn escape( x: &str ) -> String
+{
+    let mut ret = String::new();
+
+    for c in x.chars()
+    {
+        if "*?[]{}\\".contains( c )
+        {
+            ret.push( '\\' );
+        }
+
+        ret.push( c );
+    }
+
+    ret
+}
+
+/// `x` as a pattern, or as the name itself ( `prefix`: the names starting with it ) if `found` holds for that one.
+/// `[` and `{` may be a part of a name ( `Album [Deluxe]` ), so the name as it is comes first.
+pub fn literal_first( x: &str, prefix: bool, found: impl Fn( &Pattern ) -> bool ) -> Pattern
+{
+    let literal = Pattern::new( &format!( "{}{}", escape( x ), if prefix { "*" } else { "" } ) );
+
+    if found( &literal ) { literal } else { Pattern::new( x ) }
+}
+
+/// Expands the braces, `Disc{1,2}` to `Disc1` and `Disc2`. Braces nest, and `{x}` without a comma stays as it is.
+pub fn expand_braces( x: &str ) -> Vec< String >
+{
+    let chars : Vec<char> = x.chars().collect();
+
+    let mut depth   = 0;
+    let mut open    = None;
+    let mut commas  = Vec::< usize >::new();
+    let mut i       = 0;
+
+    while i < chars.len()
+    {
+        match chars[ i ]
+        {
+            '\\'                        => i += 1
+        ,   '{'                         => { if depth == 0 { open = Some( i ); commas.clear(); } depth += 1; }
+        ,   ',' if depth == 1           => commas.push( i )
+        ,   '}' if depth > 0            =>
+            {
+                depth -= 1;
+
+                if depth == 0
+                {
+                    let start = open.unwrap_or( 0 );
+
+                    if !commas.is_empty()
+                    {
+                        let head : String = chars[ .. start ].iter().collect();
+                        let tail : String = chars[ i + 1 .. ].iter().collect();
+
+                        let mut bounds = vec![ start ];
+                        bounds.extend( &commas );
+                        bounds.push( i );
+
+                        return bounds.windows( 2 ).flat_map( |w|
+                            {
+                                let alt : String = chars[ w[0] + 1 .. w[1] ].iter().collect();
+
+                                expand_braces( &format!( "{}{}{}", head, alt, tail ) )
+                            }
+                        ).collect();
+                    }
+                }
+            }
+        ,   _                           => {}
+        }
+
+        i += 1;
+    }
+
+    vec![ String::from( x ) ]
+}
+
+/// A part of a pattern component.
+#[derive(Debug, Clone)]
+enum Token
+{
+    /// `*`
+    Star
+,   /// `?`
+    Any
+,   /// `[...]`, the members and true if negated.
+    Class( Vec<char>, bool )
+,   Char( char )
+}
+
+impl Token
+{
+    /// True if the token matches `c`. `Star` matches as many as it needs, here none.
+    fn matches( &self, c: char ) -> bool
+    {
+        match self
+        {
+            Token::Star                 => false
+        ,   Token::Any                  => true
+        ,   Token::Char( x )            => *x == c
+        ,   Token::Class( x, negate )   =>
+            {
+                let mut i   = 0;
+                let mut hit = false;
+
+                while i < x.len()
+                {
+                    if i + 2 < x.len() && x[ i + 1 ] == '-'
+                    {
+                        hit = hit || ( x[ i ] ..= x[ i + 2 ] ).contains( &c );
+                        i += 3;
+                    }
+                    else
+                    {
+                        hit = hit || x[ i ] == c;
+                        i += 1;
+                    }
+                }
+
+                hit != *negate
+            }
+        }
+    }
+}
+
+/// The tokens of a component. `[abc]` `[a-z]` `[!a]` are classes, `\` escapes, and a `[` not closed is itself.
+fn tokens( pat: &[char] ) -> Vec< Token >
+{
+    let mut ret = Vec::< Token >::new();
+    let mut i   = 0;
+
+    while i < pat.len()
+    {
+        let ( token, len ) = match pat[ i ]
+        {
+            '*'                         => ( Token::Star, 1 )
+        ,   '?'                         => ( Token::Any, 1 )
+        ,   '\\' if i + 1 < pat.len()   => ( Token::Char( pat[ i + 1 ] ), 2 )
+        ,   '['                         => class( &pat[ i + 1 .. ] ).map( |( x, len )| ( x, len + 1 ) ).unwrap_or( ( Token::Char( '[' ), 1 ) )
+        ,   c                           => ( Token::Char( c ), 1 )
+        };
+
+        ret.push( token );
+        i += len;
+    }
+
+    ret
+}
+
+/// The class after `[`, and the length up to `]`. None if it is not closed.
+fn class( pat: &[char] ) -> Option< ( Token, usize ) >
+{
+    let negate  = matches!( pat.first(), Some( '!' ) | Some( '^' ) );
+    let start   = if negate { 1 } else { 0 };
+
+    // `]` first is a member
+    let end = start + 1 + pat.get( start + 1 .. )?.iter().position( |x| *x == ']' )?;
+
+    Some( ( Token::Class( pat[ start .. end ].to_vec(), negate ), end + 1 ) )
+}
+
+/// Matches `items` with `pat`, where `is_star` items match any number of items and `matches` the others one.
+/// Going back to the last star only, so it takes time in proportion to the lengths, not their power.
+fn match_seq< P, I >( pat: &[ P ], items: &[ I ], is_star: impl Fn( &P ) -> bool, matches: impl Fn( &P, &I ) -> bool ) -> bool
+{
+    let mut p       = 0;
+    let mut i       = 0;
+    let mut star    = None;
+
+    while i < items.len()
+    {
+        match pat.get( p )
+        {
+            Some( x ) if is_star( x )               => { star = Some( ( p, i ) ); p += 1; }
+        ,   Some( x ) if matches( x, &items[ i ] )  => { p += 1; i += 1; }
+        ,   _                                       =>
+            {
+                // the last star takes one item more
+                match star
+                {
+                    Some( ( sp, si ) )  => { star = Some( ( sp, si + 1 ) ); p = sp + 1; i = si + 1; }
+                ,   None                => return false
+                }
+            }
+        }
+    }
+
+    pat[ p .. ].iter().all( is_star )
+}
+
+/// Matches one path component. `*` and `?` do not match `/`, `[abc]` `[a-z]` `[!a]` match a character, `\` escapes.
+fn match_name( pat: &[char], name: &[char] ) -> bool
+{
+    match_seq( &tokens( pat ), name, |x| matches!( x, Token::Star ), |x, c| x.matches( *c ) )
+}
+
+/// Matches the components of a path. `**` matches any number of directories.
+fn match_path( pat: &[ Vec<char> ], path: &[ Vec<char> ] ) -> bool
+{
+    match_seq( pat, path, |x| x.as_slice() == [ '*', '*' ], |x, name| match_name( x, name ) )
+}
+
+fn split( path: &str ) -> Vec< Vec<char> >
+{
+    path.split( '/' ).filter( |x| !x.is_empty() ).map( |x| x.chars().collect() ).collect()
+}
+
+/// A path pattern, the same for every command taking paths.
+/// `*` `?` `[...]` match in a component, `**` across directories, and `{a,b}` is expanded first.
+#[derive(Debug, Clone)]
+pub struct Pattern
+{
+    alts:   Vec< Vec< Vec<char> > >
+,   base:   String
+}
+
+impl Pattern
+{
+    /// `pattern` is a canonical path, without the leading `/`.
+    pub fn new( pattern: &str ) -> Pattern
+    {
+        let expanded = expand_braces( pattern );
+
+        let alts : Vec< Vec< Vec<char> > > = expanded.iter().map( |x| split( x ) ).collect();
+
+        // the directories without wildcards, common to all the alternatives
+        let mut base : Option< Vec<&str> > = None;
+
+        for x in &expanded
+        {
+            let lits : Vec<&str> = x.split( '/' ).filter( |x| !x.is_empty() ).collect();
+            let lits = &lits[ .. lits.len().saturating_sub( 1 ) ];
+            let lits : Vec<&str> = lits.iter().take_while( |x| !has_magic( x ) ).copied().collect();
+
+            base = Some( match base
+                {
+                    None        => lits
+                ,   Some( b )   => b.iter().zip( lits.iter() ).take_while( |( a, b )| a == b ).map( |x| *x.0 ).collect()
+                }
+            );
+        }
+
+        Pattern { alts, base: base.unwrap_or_default().join( "/" ) }
+    }
+
+    /// The directory to list: the part before the first wildcard.
+    pub fn base( &self ) -> &str
+    {
+        &self.base
+    }
+
+    /// True if the wildcards are in the last component only, so listing `base` is enough.
+    pub fn is_shallow( &self ) -> bool
+    {
+        let depth = split( &self.base ).len();
+
+        self.alts.iter().all( |x| x.len() == depth + 1 && x.last().is_some_and( |x| x.as_slice() != [ '*', '*' ] ) )
+    }
+
+    /// The same pattern at any depth under `base`, `Jazz/*.flac` as `Jazz/**/*.flac`.
+    pub fn anywhere( &self ) -> Pattern
+    {
+        let depth = split( &self.base ).len();
+
+        let alts = self.alts.iter().map( |x|
+            {
+                let mut x = x.clone();
+                x.insert( depth.min( x.len() ), vec![ '*', '*' ] );
+                x
+            }
+        ).collect();
+
+        Pattern { alts, base: self.base.clone() }
+    }
+
+    /// True if `path` matches.
+    pub fn matches( &self, path: &str ) -> bool
+    {
+        let path = split( path );
+
+        self.alts.iter().any( |x| match_path( x, &path ) )
+    }
+
+    /// True if `path` or a directory above it matches.
+    pub fn matches_within( &self, path: &str ) -> bool
+    {
+        let path = split( path );
+
+        ( 1 ..= path.len() ).any( |n| self.alts.iter().any( |x| match_path( x, &path[ .. n ] ) ) )
+    }
+
+    /// True if the last component of the pattern matches `name`.
+    pub fn matches_name( &self, name: &str ) -> bool
+    {
+        let name : Vec<char> = name.chars().collect();
+
+        self.alts.iter().any( |x| x.last().is_some_and( |x| match_name( x, &name ) ) )
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn matches( pattern: &str, path: &str ) -> bool
+    {
+        Pattern::new( pattern ).matches( path )
+    }
+
+    #[test]
+    fn wildcards()
+    {
+        assert!( matches( "Jazz/*.flac", "Jazz/a.flac" ) );
+        assert!( !matches( "Jazz/*.flac", "Jazz/Sub/c.flac" ) );
+        assert!( matches( "Jazz/?.flac", "Jazz/b.flac" ) );
+        assert!( !matches( "Jazz/?.flac", "Jazz/bb.flac" ) );
+        assert!( matches( "*", "Jazz" ) );
+        assert!( matches( "J*z*", "Jazz" ) );
+        assert!( !matches( "J*x*", "Jazz" ) );
+    }
+
+    #[test]
+    fn classes()
+    {
+        assert!( matches( "[a-c].flac", "b.flac" ) );
+        assert!( !matches( "[a-c].flac", "d.flac" ) );
+        assert!( matches( "[!a]x", "bx" ) );
+        assert!( !matches( "[^a]x", "ax" ) );
+        assert!( matches( "[]a]", "]" ) );
+        assert!( matches( "Disc[12]/*", "Disc2/a.flac" ) );
+    }
+
+    #[test]
+    fn literal_brackets()
+    {
+        // a class unless escaped, the callers try the name as it is first
+        assert!( !matches( "Artist/Album [Deluxe]", "Artist/Album [Deluxe]" ) );
+        assert!( matches( &escape( "Artist/Album [Deluxe]" ), "Artist/Album [Deluxe]" ) );
+        assert!( matches( "Artist/Album \\[Deluxe\\]", "Artist/Album [Deluxe]" ) );
+        assert!( matches( &escape( "Live {2001} *?" ), "Live {2001} *?" ) );
+        assert!( !matches( &escape( "a*" ), "ab" ) );
+        assert!( matches( &escape( "a\\b" ), "a\\b" ) );
+
+        // not closed
+        assert!( matches( "Live [Disc", "Live [Disc" ) );
+        assert!( matches( "{x}", "{x}" ) );
+    }
+
+    #[test]
+    fn literal_first_then_pattern()
+    {
+        let names = [ "Album [Deluxe]", "Album D", "Album e" ];
+        let pick = |x: &str, prefix: bool|
+        {
+            let p = literal_first( x, prefix, |p| names.iter().any( |x| p.matches_name( x ) ) );
+
+            names.iter().filter( |x| p.matches_name( x ) ).copied().collect::< Vec<&str> >()
+        };
+
+        assert_eq!( pick( "Album [Deluxe]", false ), vec![ "Album [Deluxe]" ] );
+        assert_eq!( pick( "Album [De]", false ), vec![ "Album D", "Album e" ] );
+        assert_eq!( pick( "Album [D", true ), vec![ "Album [Deluxe]" ] );
+        assert_eq!( pick( "Album [D]*", true ), vec![ "Album D" ] );
+    }
+
+    #[test]
+    fn braces()
+    {
+        assert_eq!( expand_braces( "Disc{1,2}" ), vec![ "Disc1", "Disc2" ] );
+        assert_eq!( expand_braces( "{a,b{c,d}}x" ), vec![ "ax", "bcx", "bdx" ] );
+        assert_eq!( expand_braces( "a\\{b,c}" ), vec![ "a\\{b,c}" ] );
+
+        assert!( matches( "*/Disc{1,2}/*", "Jazz/Disc1/a.flac" ) );
+        assert!( matches( "*/Disc{1,2}/*", "Jazz/Disc2/b.flac" ) );
+        assert!( !matches( "*/Disc{1,2}/*", "Jazz/Disc3/c.flac" ) );
+    }
+
+    #[test]
+    fn across_directories()
+    {
+        assert!( matches( "Jazz/**/*.flac", "Jazz/a.flac" ) );
+        assert!( matches( "Jazz/**/*.flac", "Jazz/Sub/Sub/c.flac" ) );
+        assert!( !matches( "Jazz/**/*.flac", "Rock/x.flac" ) );
+        assert!( !matches( "Jazz/**/*.flac", "Jazz/Sub" ) );
+        assert!( matches( "**", "Jazz/Sub/c.flac" ) );
+        assert!( matches( "**/Sub/**", "Jazz/Sub/c.flac" ) );
+
+        let p = Pattern::new( "Jazz/Sub/*.flac" );
+
+        assert_eq!( p.base(), "Jazz/Sub" );
+        assert!( p.is_shallow() );
+        assert!( !Pattern::new( "Jazz/**/*.flac" ).is_shallow() );
+        assert!( p.anywhere().matches( "Jazz/Sub/x/y/c.flac" ) );
+        assert!( Pattern::new( "Jazz/S*" ).matches_within( "Jazz/Sub/c.flac" ) );
+        assert!( p.matches_name( "c.flac" ) );
+        assert_eq!( Pattern::new( "{Jazz/a,Jazz/b}/*" ).base(), "Jazz" );
+    }
+
+    #[test]
+    fn no_backtracking_blowup()
+    {
+        let name = "a".repeat( 40 );
+
+        assert!( !matches( "*a*a*a*a*a*a*a*b", &name ) );
+        assert!( matches( "*a*a*a*a*a*a*a*a", &name ) );
+
+        let path = vec![ "a"; 40 ].join( "/" );
+
+        assert!( !matches( "**/a/**/a/**/a/**/a/**/a/**/b", &path ) );
+        assert!( matches( "**/a/**/a/**/a/**/a/**/a/**", &path ) );
+    }
+}
@@ -0,0 +1,39 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		lib.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+//! MPD ( Music Player Daemon ) client library used by the mpdsh shell.
+//!
+//! ```no_run
+//! let addr = mpdsh::ConnAddr::new( "localhost", "6600" );
+//! let client = addr.connect( false, None, None ).unwrap();
+//!
+//! let status = client.status().unwrap();
+//!
+//! if let Some( song ) = client.current_song().unwrap()
+//! {
+//!     println!( "{}: {}", status.state, song.display_name() );
+//! }
+//! ```
+
+#[macro_use]
+extern crate lazy_static;
+extern crate regex;
+
+pub mod error;
+pub mod stream;
+pub mod protocol;
+pub mod types;
+pub mod client;
+
+pub use crate::error::{ Ack, AckCode };
+pub use crate::stream::{ MpdStream, ConnAddr };
+#[cfg(unix)]
+pub use crate::stream::UnixAddr;
+pub use crate::protocol::{ ExecOk, ExecResult, BinaryResult, quote_arg, make_command_1, make_command_2 };
+pub use crate::types::{ EntryKind, ListEntry, Song, PlayState, Status, Output, Playlist, Stats };
+pub use crate::client::{ Client, IdleNotify, ConnNotify };
@@ -7,446 +7,513 @@
 //	----------------------------------------------------------------------------
 
 use std::env;
-use std::io::prelude::*;
-use std::io::{ BufRead, BufReader, Write };
-use std::net;
-use std::net::TcpStream;
-use std::fmt;
-use std::borrow::Cow;
-use std::thread;
-use std::time::Duration;
-use std::sync::mpsc;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::io::{ self, BufRead, IsTerminal, Write };
 use std::str::FromStr;
-use std::cell::{ RefCell, Ref, RefMut };
+use std::cell::{ Cell, RefCell, Ref, RefMut };
 
 extern crate getopts;
 extern crate shell_words;
-extern crate wildmatch;
 
 #[macro_use]
 extern crate lazy_static;
 extern crate regex;
 
-use rustyline::error::ReadlineError;
-use rustyline;
-use rustyline::{ completion::Completer, Context };
-
-struct ExecOk
-{
-    flds:       Vec<(String,String)>
-,   bin:        Option<Vec<u8>>
-}
+mod config;
+mod parse;
+mod output;
+mod commands;
+mod pick;
+mod vdir;
+mod filter;
+mod tree;
+mod glob;
+mod cache;
+
+use crate::parse::{ Chain, Redirect };
+use crate::output::Sink;
+use crate::commands::ArgKind;
 
-struct ExecErr
-{
-    err_code:   i32
-,   cmd_index:  i32
-,   cur_cmd:    Option<String>
-,   msg_text:   Option<String>
-}
-
-impl fmt::Display for ExecErr
-{
-    fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
-    {
-        match &self.msg_text
-        {
-            Some( x )   => write!( f, "code:{} msg:{}", self.err_code, x )
-        ,   None        => write!( f, "code:{}", self.err_code )
-        }
-    }
-}
+use rustyline::error::ReadlineError;
+use rustyline::{ completion::Completer, Context, ExternalPrinter };
 
-type ExecResult = Result<ExecOk, ExecErr>;
+use mpdsh::{ Client, Ack, AckCode, ConnAddr, IdleNotify, ConnNotify, ListEntry, EntryKind, Output, Song, make_command_1, make_command_2, quote_arg };
 
-#[derive(Debug, Clone)]
-struct ListEntry
+/// print! to the current output of Mpdsh ( the terminal, a pipe, a file or a `$(…)` capture ).
+macro_rules! out
 {
-    name:       String
-,   name_type:  String
-,   flds:       Vec<(String,String)>
+    ( $sh:expr, $( $arg:tt )* ) => { $sh.write_out( format_args!( $( $arg )* ) ) };
 }
 
-impl ListEntry
+/// println! to the current output of Mpdsh.
+macro_rules! outln
 {
-    fn new() -> ListEntry
-    {
-        ListEntry{ name: String::new(), name_type: String::new(), flds: Vec::<(String,String)>::new() }
-    }
+    ( $sh:expr ) => { $sh.write_out( format_args!( "\n" ) ) };
+    ( $sh:expr, $( $arg:tt )* ) => { $sh.write_out( format_args!( "{}\n", format_args!( $( $arg )* ) ) ) };
 }
 
 struct Mpdsh
 {
-    worker_handle:  thread::JoinHandle<()>
-,   htx:            mpsc::Sender<String>
-,   wrx:            mpsc::Receiver<ExecResult>
-,   version:        String
+    client:         Client
 ,   curdir:         String
 ,   args:           Vec<String>
-,   args_opt:       Vec<String>
+,   args_opt:       Vec< ( char, Option<String> ) >
+,   failed:         Cell<bool>
+,   /// Ends a line at the first failed `;` command. Set for scripts without --keep-going.
+    stop_on_error:  bool
+,   line_args:      Vec<String>
+,   aliases:        BTreeMap<String, String>
+,   alias_depth:    usize
+,   /// False while running ~/.mpdshrc, so its aliases and bookmarks are not written to the config file.
+    save_config:    bool
+,   picker:         Option<String>
+,   /// The directory before the last cd, for `cd -`.
+    prevdir:        Option<String>
+,   /// pushd / popd. The top is the last.
+    dirstack:       Vec<String>
+,   bookmarks:      BTreeMap<String, String>
+,   prompt_depth:   Option<usize>
+,   cache:          cache::DirCache
+,   out:            RefCell< Vec< Sink > >
+,   quit:           bool
 }
 
 impl Mpdsh
 {
-    fn new( stream: TcpStream, opt_protolog: bool ) -> Result< Self, () >
+    fn new( client: Client ) -> Self
     {
-        let mut reader = BufReader::new( &stream );
-        let mut buf = String::new();
-
-        reader.read_line( &mut buf ).expect( "failed to read from socket" );
+        Mpdsh
+        {
+            client
+        ,   curdir:         String::from( "/" )
+        ,   args:           Vec::new()
+        ,   args_opt:       Vec::new()
+        ,   failed:         Cell::new( false )
+        ,   stop_on_error:  false
+        ,   line_args:      Vec::new()
+        ,   aliases:        BTreeMap::new()
+        ,   alias_depth:    0
+        ,   save_config:    true
+        ,   picker:         None
+        ,   prevdir:        None
+        ,   dirstack:       Vec::new()
+        ,   bookmarks:      BTreeMap::new()
+        ,   prompt_depth:   None
+        ,   cache:          cache::DirCache::new( cache::Mode::Memory, "" )
+        ,   out:            RefCell::new( Vec::new() )
+        ,   quit:           false
+        }
+    }
 
-        println!( "connected {}", &buf );
+    /// `mpdsh:/a/b/c> `, or `mpdsh:.../b/c> ` with prompt_depth 2.
+    fn prompt( &self ) -> String
+    {
+        let parts : Vec<&str> = self.curdir.split( '/' ).filter( |x| !x.is_empty() ).collect();
 
-        if !buf.starts_with("OK MPD ")
+        match self.prompt_depth
         {
-            return Err(());
+            Some( n ) if parts.len() > n    => format!( "mpdsh:.../{}> ", parts[ parts.len() - n .. ].join( "/" ) )
+        ,   _                               => format!( "mpdsh:{}> ", &self.curdir )
         }
+    }
 
-        let version = String::from( buf[7..].trim() );
+    /// Keeps the words of a command line. They are split into `args` and `args_opt` by the command.
+    fn setup_args( &mut self, args : Vec<String> )
+    {
+        self.args_opt.clear();
+        self.args       = args.clone();
+        self.line_args  = args;
+    }
 
-        let ( htx, hrx ) : ( mpsc::Sender<String>,      mpsc::Receiver<String> )        = mpsc::channel();
-        let ( wtx, wrx ) : ( mpsc::Sender<ExecResult>,  mpsc::Receiver<ExecResult> )    = mpsc::channel();
+    /// Runs a line of commands joined by `;`, `&&` and `||`, with `$(…)` substituted. Returns true on `quit`.
+    /// Afterwards `failed()` is true if any of the `;` commands failed ( a failure handled by `||` is not counted ).
+    fn run_line( &mut self, line : &str ) -> bool
+    {
+        self.failed.set( false );
 
-        let worker_handle  = thread::spawn( move ||
-            {
-                Self::worker( stream, hrx, wtx, opt_protolog )
-            }
-        );
+        let mut line_failed = false;
 
-        Ok( Mpdsh
+        for ( chain, cmd ) in parse::split_chain( line )
+        {
+            match chain
             {
-                worker_handle:  worker_handle
-            ,   htx:            htx
-            ,   wrx:            wrx
-            ,   version:        version
-            ,   curdir:         String::from( "/" )
-            ,   args:           Vec::new()
-            ,   args_opt:       Vec::new()
+                Chain::And  if  self.failed() => { continue; }
+            ,   Chain::Or   if !self.failed() => { continue; }
+            ,   Chain::Seq  if  self.failed() =>
+                {
+                    line_failed = true;
+
+                    if self.stop_on_error
+                    {
+                        return false;
+                    }
+                }
+            ,   _ => {}
             }
-        )
-    }
 
-    fn exec_command( &self, cmd: &str ) -> ExecResult
-    {
-        match self.htx.send( String::from( cmd ) )
-        {
-            Ok(_)   => {
+            let ( cmd, redirect ) = parse::split_redirect( &cmd );
 
-                match self.wrx.recv()
+            let cmd = match parse::expand_subst( cmd, |x| self.capture( x ) )
+            {
+                Some( x )   => { x }
+            ,   None        =>
                 {
-                    Ok(x)   => {
-                        return x;
-                    }
-                ,   Err(_)  => {}
+                    self.failed.set( true );
+                    continue;
                 }
+            };
 
-            }
-        ,   Err(_)  => {}
-        };
-
-        Err(
-            ExecErr
+            if let Some( x ) = &redirect
             {
-                err_code:   -2
-            ,   cmd_index:  0
-            ,   cur_cmd:    None
-            ,   msg_text:   None
+                match self.open_sink( x )
+                {
+                    Ok( x )     => { self.out.borrow_mut().push( x ); }
+                ,   Err( x )    =>
+                    {
+                        self.show_fail( &x );
+                        continue;
+                    }
+                }
             }
-        )
-    }
-
-    fn worker( mut stream: TcpStream, hrx : mpsc::Receiver<String>, wtx : mpsc::Sender<ExecResult>, protolog : bool )
-    {
-        let mut io_err : Option< std::io::Error > = None;
-
-        let time_out = Duration::from_secs( 10 );
 
-        'outer: loop
-        {
-            let recv = hrx.recv_timeout( time_out );
-            let ( cmd, ret ) = match recv
+            let quit = match shell_words::split( &cmd )
             {
-                Ok( x ) => { ( Cow::Owned( x )          , true ) }
-            ,   Err(_)  => { ( Cow::Borrowed( "ping" )  , false ) }
+                Ok( args )  => { self.cmdline( args ) }
+            ,   Err( err )  =>
+                {
+                    self.show_fail( &format!( "Error: {:?}", err ) );
+                    false
+                }
             };
 
-            if cmd == "quit"
+            if redirect.is_some()
             {
-                wtx.send(
-                    Ok(
-                        ExecOk
-                        {
-                            flds:   Vec::<(String, String)>::new()
-                        ,   bin:    Option::<Vec<u8>>::None
-                        }
-                    )
-                );
-
-                break 'outer;
+                self.close_sink();
             }
 
-            if let Err(x) = stream
-                .write( cmd.as_bytes() )
-                .and_then(|_| stream.write( &[0x0a] ) )
-                .and_then(|_| stream.flush() )
+            if quit
             {
-                io_err = Some( x );
-                break 'outer;
+                self.failed.set( line_failed || self.failed() );
+                return true;
             }
+        }
 
-            if protolog && ret
-            {
-                eprintln!( "> {}", cmd );
-            }
+        self.failed.set( line_failed || self.failed() );
+
+        false
+    }
 
-            let mut reader = BufReader::new( &stream );
+    /// Runs `line` and returns its output, or None if it failed.
+    fn capture( &mut self, line : &str ) -> Option<String>
+    {
+        self.out.borrow_mut().push( Sink::Capture( Vec::new() ) );
 
-            let mut buf = String::new();
+        self.run_line( line );
 
-            let mut flds            = Vec::<(String, String)>::new();
-            let mut bin             = Option::<Vec<u8>>::None;
-            let mut err_code:   i32 = 0;
-            let mut cmd_index:  i32 = 0;
-            let mut cur_cmd         = Option::<String>::None;
-            let mut msg_text        = Option::<String>::None;
+        let buf = match self.out.borrow_mut().pop().map( Sink::finish )
+        {
+            Some( Ok( ( x, _ ) ) )  => x
+        ,   _                       => Vec::new()
+        };
 
-            let send;
+        if self.failed()
+        {
+            None
+        }
+        else
+        {
+            Some( String::from_utf8_lossy( &buf ).into_owned() )
+        }
+    }
 
-            loop
+    fn open_sink( &self, redirect : &Redirect ) -> Result< Sink, String >
+    {
+        match redirect
+        {
+            Redirect::Pipe( x ) if x.is_empty() => Err( String::from( "Please specify command after `|`..." ) )
+        ,   Redirect::Pipe( x ) =>
             {
-                buf.clear();
+                let _ = io::stdout().flush();
 
-                match reader.read_line( &mut buf )
+                Sink::pipe( x, !self.out.borrow().is_empty() ).map_err( |e| format!( "error.. ({}: {})", x, e ) )
+            }
+        ,   Redirect::Write( x ) | Redirect::Append( x ) =>
+            {
+                let path = match shell_words::split( x ).as_deref()
                 {
-                    Ok(x) =>
-                    {
-                        if x == 0
-                        {
-                            break 'outer;
-                        }
-                    }
-                ,   Err(x) =>
-                    {
-                        io_err = Some( x );
-                        break 'outer;
-                    }
+                    Ok( [ path ] )  => path.clone()
+                ,   _               => return Err( String::from( "Please specify one file after `>`..." ) )
                 };
 
-                if protolog && ret
+                Sink::file( &path, matches!( redirect, Redirect::Append(_) ) ).map_err( |e| format!( "error.. ({}: {})", path, e ) )
+            }
+        }
+    }
+
+    /// Closes the sink opened by `open_sink`. The output of a pipe goes on to the outer sink.
+    fn close_sink( &self )
+    {
+        let sink = self.out.borrow_mut().pop();
+
+        match sink.map( Sink::finish )
+        {
+            Some( Ok( ( buf, ok ) ) ) =>
+            {
+                if !buf.is_empty()
                 {
-                    eprint!("< {}", buf );
+                    self.write_raw( &buf );
                 }
 
-                if buf == "OK\n"
+                if !ok
                 {
-                    send = Ok( ExecOk { flds, bin } );
-                    break;
+                    self.failed.set( true );
                 }
-                else if buf.starts_with( "ACK [" )
-                {
-                    lazy_static! {
-                        static ref RE: regex::Regex =
-                            regex::Regex::new( r"^ACK\s*\[(\d+)@(\d+)\]\s+\{([^}]*)\}\s*(.*)\n" ).unwrap();
-                    }
+            }
+        ,   Some( Err( x ) )    => self.show_fail( &format!( "error.. ({})", x ) )
+        ,   None                => {}
+        }
+    }
 
-                    if let Some( x ) = RE.captures( &buf )
-                    {
-                        err_code    = x[1].parse().unwrap();
-                        cmd_index   = x[2].parse().unwrap();
-                        cur_cmd     = Some( String::from( &x[3] ) );
-                        msg_text    = Some( String::from( &x[4] ) );
-                    };
+    /// True while the output ends up in `$(…)`, even through a pipe. Listings print bare paths then.
+    fn captured( &self ) -> bool
+    {
+        self.out.borrow().iter().any( |x| matches!( x, Sink::Capture(_) ) )
+    }
 
-                    send = Err( ExecErr { err_code, cmd_index, cur_cmd, msg_text } );
-                    break;
-                }
-                else
-                {
-                    lazy_static! {
-                        static ref RE: regex::Regex =
-                            regex::Regex::new( r"^([^:]*):\s*(.*)\n" ).unwrap();
-                    }
+    fn write_out( &self, args : std::fmt::Arguments )
+    {
+        match self.out.borrow_mut().last_mut()
+        {
+            Some( x )   => { let _ = x.write_fmt( args ); }
+        ,   None        => { let _ = io::stdout().write_fmt( args ); }
+        }
+    }
 
-                    if let Some( x ) = RE.captures( &buf )
+    fn write_raw( &self, buf : &[u8] )
+    {
+        match self.out.borrow_mut().last_mut()
+        {
+            Some( x )   => { let _ = x.write_all( buf ); }
+        ,   None        => { let _ = io::stdout().write_all( buf ); }
+        }
+    }
+
+    /// Runs one command line. Returns true on `quit`. `failed()` tells whether the command reported an error.
+    fn cmdline( &mut self, args : Vec<String> ) -> bool
+    {
+        self.setup_args( args );
+        self.failed.set( false );
+
+        if let Some( body ) = self.line_args.first().and_then( |x| self.aliases.get( x ) ).cloned()
+        {
+            return self.run_alias( &body );
+        }
+
+        if !self.args.is_empty()
+        {
+            match commands::find( &self.args[0] )
+            {
+                Some( x )   =>
+                {
+                    match x.parse( &self.line_args ).and_then( |p| x.check( &p ).map( |_| p ) )
                     {
-                        if &x[1] == "binary"
+                        Ok( p )     =>
                         {
-                            let binlen = x[2].parse().unwrap();
-                            let mut buf = Vec::<u8>::with_capacity(binlen);
-                            unsafe
-                            {
-                                buf.set_len( binlen );
-                            }
+                            self.args       = p.args;
+                            self.args_opt   = p.opts;
+                            self.args[0]    = String::from( x.name );
 
-                            match reader.read( &mut buf )
-                            {
-                                Ok(_) =>
-                                {
-                                    bin = Some( buf )
-                                }
-                            ,   Err(x) =>
-                                {
-                                    io_err = Some( x );
-                                    break 'outer;
-                                }
-                            }
-                        }
-                        else
-                        {
-                            flds.push(
-                                (
-                                    String::from( x[1].trim() )
-                                ,   String::from( x[2].trim() )
-                                )
-                            );
+                            ( x.run )( self );
                         }
+                    ,   Err( e )    => self.show_usage( x, &e )
                     }
                 }
-            }
-
-            if ret
-            {
-                wtx.send( send );
+            ,   None        => self.cmd_unknown()
             }
         }
 
-        if io_err.is_some()
-        {
-            eprintln!( "" );
-            eprintln!( "{:?}", io_err.as_ref().unwrap() );
-            std::process::exit(1);
-        }
+        std::mem::take( &mut self.quit )
+    }
 
-        else if let Err(x) = stream.shutdown( std::net::Shutdown::Both )
-        {
-            eprintln!( "" );
-            eprintln!( "{:?}", x );
-        }
+    fn failed( &self ) -> bool
+    {
+        self.failed.get()
     }
 
-    fn prompt( &self ) -> String
+    fn has_opt( &self, opt : char ) -> bool
     {
-        format!( "mpdsh:{}> ", &self.curdir )
+        self.args_opt.iter().any( |x| x.0 == opt )
     }
 
-    fn setup_args( &mut self, args : Vec<String> )
+    /// The value of the option. The last one wins.
+    fn opt_value( &self, opt : char ) -> Option< &str >
     {
-        self.args.clear();
-        self.args_opt.clear();
+        self.args_opt.iter().rev().find( |x| x.0 == opt ).and_then( |x| x.1.as_deref() )
+    }
 
-        for x in args
+    /// Completion candidates for the last argument of `args`, and the length of the text they replace.
+    /// `new_arg` is true when the line ends with a space, so a new argument is started.
+    fn cmdline_hint( &mut self, args : Vec<String>, new_arg : bool ) -> ( Vec<String>, usize )
+    {
+        self.setup_args( args );
+
+        if self.args.is_empty()
         {
-            if x.starts_with( "#" )
-            {
-                break;
-            }
+            return ( self.cmdlist_with_aliases(), 0 )
+        }
 
-            if x.starts_with( "-" )
-            {
-                self.args_opt.push( x );
-            }
-            else
-            {
-                self.args.push( x );
-            }
+        if self.args.len() == 1 && !new_arg
+        {
+            return Self::hint_names( self.cmdlist_with_aliases(), &self.args[0] )
         }
-    }
 
-    fn cmdline( &mut self, args : Vec<String> ) -> bool
-    {
-        self.setup_args( args );
+        let cmd = match commands::find( &self.args[0] )
+        {
+            Some( x )   => x
+        ,   None        => return ( Vec::<String>::new(), 0 )
+        };
 
-        if !self.args.is_empty()
+        if !new_arg && commands::is_option( &self.args[ self.args.len() - 1 ] )
         {
-            match self.args[0].as_str()
-            {
-                "cd"                    => self.cmd_cd()
-            ,   "ls"                    => self.cmd_ls()
+            return ( Vec::<String>::new(), 0 )
+        }
+
+        // the value of an option ( `tree -d 2` ) has nothing to complete
+        let before = if new_arg { &self.line_args[ .. ] } else { &self.line_args[ .. self.line_args.len() - 1 ] };
+
+        if cmd.needs_value( before )
+        {
+            return ( Vec::<String>::new(), 0 )
+        }
+
+        // count the arguments only, the line may still be incomplete
+        self.args = match cmd.parse( &self.line_args )
+        {
+            Ok( x )     => x.args
+        ,   Err(_)      => self.line_args.iter().filter( |x| !commands::is_option( x ) ).cloned().collect()
+        };
 
-            ,   "pl"        | "plist"   => self.cmd_pl()
-            ,   "add"       | "a"       => self.cmd_ls()
-            ,   "add_top"   | "at"      => self.cmd_ls()
-            ,   "add_uri"               => self.cmd_with_args( "addid", 2 )
-            ,   "del"                   => self.cmd_with_args( "delete", 1 )
-            ,   "clr"                   => self.cmd_with_args( "clear", 0 )
-            ,   "move"                  => self.cmd_with_args( "move", 2 )
+        if self.args.len() < if new_arg { 1 } else { 2 }
+        {
+            return ( Vec::<String>::new(), 0 )
+        }
 
-            ,   "play"      | "p"       => self.cmd_with_args( "play", 1 )
-            ,   "stop"      | "s"       => self.cmd_with_args( "stop", 0 )
-            ,   "pause"     | "u"       => self.cmd_with_args( "pause 1", 0 )
-            ,   "resume"    | "e"       => self.cmd_with_args( "pause 0", 0 )
-            ,   "prev"      | "r"       => self.cmd_with_args( "previous", 0 )
-            ,   "next"      | "n"       => self.cmd_with_args( "next", 0 )
+        let index   = if new_arg { self.args.len() - 1 } else { self.args.len() - 2 };
+        let partial = if new_arg { String::new() } else { self.args[ self.args.len() - 1 ].clone() };
+        let prev    = self.args[ index ].clone();
 
-            ,   "random"                => self.cmd_switch( "random" )
-            ,   "repeat"                => self.cmd_switch( "repeat" )
-            ,   "single"                => self.cmd_switch( "single" )
-            ,   "volume"    | "v"       => self.cmd_switch( "setvol" )
+        // the hint functions look at args[1] only
+        self.args = if partial.is_empty() { vec![ self.args[0].clone() ] } else { vec![ self.args[0].clone(), partial.clone() ] };
 
-            ,   "status"    | "st"      => self.cmd_status()
+        let names = | x : Vec<String> | Self::hint_names( x, &partial );
 
-            ,   "update"                => self.cmd_with_args( "update", 1 )
-            ,   "cmd"                   => self.cmd_cmd()
+        let kind = cmd.arg( index ).map( |x| x.kind );
 
-            ,   "help"      | "h"       => self.cmd_help()
-            ,   "quit"      | "q"       => { self.cmd_quit(); return true; }
-            ,   _                       => self.cmd_unknown()
-            }
+        // the bookmarks complete as directories
+        if partial.starts_with( ':' ) && !partial.contains( '/' ) && matches!( kind, Some( ArgKind::Dir ) | Some( ArgKind::Path ) )
+        {
+            return names( self.bookmarks.keys().map( |x| format!( ":{}", x ) ).collect() );
         }
 
-        false
+        match kind
+        {
+            Some( ArgKind::Dir )        => self.hint_entry( false )
+        ,   Some( ArgKind::Path )       => self.hint_entry( true )
+        ,   Some( ArgKind::Command )    => names( self.cmdlist_with_aliases() )
+        ,   Some( ArgKind::Alias )      => names( self.aliases.keys().cloned().collect() )
+        ,   Some( ArgKind::Bookmark )   => names( self.bookmarks.keys().cloned().collect() )
+        ,   Some( ArgKind::MpdCommand ) => names( self.client.commands().unwrap_or_default() )
+        ,   Some( ArgKind::Pos )        => ( self.hint_queue( &partial, false ), partial.len() )
+        ,   Some( ArgKind::Song )       => ( self.hint_queue( &partial, true ), partial.len() )
+        ,   Some( ArgKind::Playlist ) if partial.contains( '/' ) => self.hint_entry( false )
+        ,   Some( ArgKind::Playlist )   => names( self.client.playlists().map( |x| x.into_iter().map( |x| x.name ).collect() ).unwrap_or_default() )
+        ,   Some( ArgKind::Output )     => names( self.client.outputs().map( |x| x.into_iter().map( |x| x.name ).collect() ).unwrap_or_default() )
+        ,   Some( ArgKind::Filter )     => self.hint_filter( &prev, &partial )
+        ,   Some( ArgKind::Choice( x ) )=> names( x.iter().map( |x| String::from( *x ) ).collect() )
+        ,   Some( ArgKind::Word ) | None=> ( Vec::<String>::new(), 0 )
+        }
     }
 
-    fn has_opt( &self, opt : &str ) -> bool
+    /// The names starting with `partial` ( ignoring case ), and the length of `partial` they replace.
+    fn hint_names( names : Vec<String>, partial : &str ) -> ( Vec<String>, usize )
     {
-        self.args_opt.iter().find( |&x| x == opt ) != None
+        let lower = partial.to_lowercase();
+
+        ( names.into_iter().filter( |x| x.to_lowercase().starts_with( &lower ) ).map( Self::hint_quote ).collect(), partial.len() )
     }
 
-    fn cmdline_hint( &mut self, args : Vec<String> ) -> ( Vec<String>, usize )
+    /// Quotes a completion candidate that would be split or misread by the command line.
+    fn hint_quote( val : String ) -> String
     {
-        self.setup_args( args );
-
-        if self.args.is_empty()
+        if val.contains( |c : char| c.is_whitespace() || "\"'\\;|&$#><".contains( c ) )
         {
-            return ( Self::cmdlist(), 0 )
+            format!( "\"{}\"", val.replace( '\\', "\\\\" ).replace( '"', "\\\"" ).replace( '$', "\\$" ) )
         }
         else
         {
-            match self.args[0].as_str()
-            {
-                "cd"    => { return self.hint_entry( false ); }
-            ,   "ls" | "add" | "a"
-                        => { return self.hint_entry( true ); }
-            ,   "help"  => {
-                    if self.args.len() == 1
-                    {
-                        return ( Self::cmdlist(), 0 )
-                    }
-                }
-            ,   _       => {}
-            }
+            val
         }
-
-        ( Vec::<String>::new(), 0 )
     }
 
     fn get_arge1_path( &self ) -> String
     {
-        let dir;
+        self.get_arg_path( 1 )
+    }
+
+    fn get_arg_path( &self, index : usize ) -> String
+    {
+        self.make_path( &self.args[ index ] )
+    }
+
+    /// The canonical path of `arg`, relative to the current directory. `:NAME/...` starts at the bookmark NAME.
+    fn make_path( &self, arg : &str ) -> String
+    {
+        let arg = self.expand_bookmark( arg );
 
-        if self.args[1].starts_with( "/" )
+        let dir = if arg.starts_with( '/' )
         {
-            dir = String::from( format!( "{}", &self.args[1] ) );
+            arg
         }
         else
         {
-            dir = String::from( format!( "{}/{}", self.curdir, &self.args[1] ) )
-        }
+            format!( "{}/{}", self.curdir, &arg )
+        };
 
         Self::make_canonical_path( &dir )
     }
 
+    /// `:NAME` and `:NAME/...` with the directory of the bookmark NAME. Anything else as it is.
+    fn expand_bookmark( &self, arg : &str ) -> String
+    {
+        if let Some( x ) = arg.strip_prefix( ':' )
+        {
+            let ( name, rest ) = x.split_once( '/' ).unwrap_or( ( x, "" ) );
+
+            if let Some( dir ) = self.bookmarks.get( name )
+            {
+                return format!( "{}/{}", dir, rest );
+            }
+        }
+
+        String::from( arg )
+    }
+
+    /// The stored playlist named by `arg`. A path to it, like `/.playlists/NAME`, names it as well.
+    fn playlist_name( &self, arg : &str ) -> String
+    {
+        if arg.contains( '/' )
+        {
+            let path = if arg.starts_with( '/' ) { String::from( arg ) } else { format!( "{}/{}", self.curdir, arg ) };
+
+            if let Some( vdir::VirtualDir::Playlist { name: Some( x ), file: None } ) = vdir::VirtualDir::parse( &Self::make_canonical_path( &path ) )
+            {
+                return x;
+            }
+        }
+
+        String::from( arg )
+    }
+
     fn make_canonical_path( path : &str ) -> String
     {
         let mut parts : Vec<&str> = Vec::new();
@@ -470,7 +537,7 @@ impl Mpdsh
         let mut p_dir  = String::new();
         let mut c_name = String::new();
 
-        if parts.len() >= 1
+        if !parts.is_empty()
         {
             c_name = String::from( parts[ parts.len() - 1 ] )
         }
@@ -483,137 +550,354 @@ impl Mpdsh
         ( p_dir, c_name )
     }
 
-    fn split_listfiles( flds : Vec< ( String, String ) > ) -> Vec< ListEntry >
-    {
-        let mut ret = Vec::< ListEntry >::new();
-
-        let mut le = ListEntry::new();
-
-        for ( k, v ) in flds.iter().rev()
-        {
-            if k == "directory" || k == "file" || k == "playlist"
-            {
-                le.name = v.to_string();
-                le.name_type = k.to_string();
-                le.flds.reverse();
-                ret.push( le );
-                le = ListEntry::new();
-            }
-            else
-            {
-                le.flds.push( ( k.to_string(), v.to_string() ) );
-            }
-        }
-
-        ret.reverse();
-        ret
-    }
-
     fn format_duration( sec_str: &str ) -> Result< String, () >
     {
         if let Ok( x ) = f32::from_str( sec_str.trim() )
         {
             let x = x as i32;
             let sec = x % 60;
-            let min =  ( x - sec ) / 60;
-            let hour = ( x - sec - min * 60 ) / 60 * 60;
+            let min = x / 60 % 60;
+            let hour = x / 3600;
 
-            return Ok( String::from( format!( "{:02}:{:02}:{:02}", hour, min, sec ) ) )
+            return Ok( format!( "{:02}:{:02}:{:02}", hour, min, sec ) )
         }
 
         Err( () )
     }
 
-    fn cmd_cd( &mut self )
+    /// Lists `dir` ( canonical ) with listfiles, or lsinfo for `info` ( with tags ).
+    /// The virtual tag directories ( `.artist/...` ) are listed with list and find.
+    fn list_dir( &self, dir : &str, info : bool ) -> Result< Vec< ListEntry >, Ack >
     {
-        if self.args.len() >= 2
+        match vdir::VirtualDir::parse( dir )
         {
-            let dir = self.get_arge1_path();
-            let dir = Self::make_canonical_path( &dir );
-
-            let ( p_dir, c_name ) = Self::make_parent_path( &dir );
+            Some( x )       => x.list( &self.client )
+        ,   None if info    => self.list_cached( "lsinfo", dir )
+        ,   None            => self.list_cached( "listfiles", dir )
+        }
+    }
 
-            let command = &Self::make_command_1( "listfiles", &p_dir );
+    /// `cmd1 dir` ( lsinfo, listfiles, listall or listallinfo ) from the cache, or from MPD.
+    fn list_cached( &self, cmd1 : &str, dir : &str ) -> Result< Vec< ListEntry >, Ack >
+    {
+        let cmd = make_command_1( cmd1, dir );
 
-            match self.exec_command( &command )
+        // without the idle listener ( -c, -f, stdin ) nothing tells the database changed, `stats` does before each listing
+        let version = match self.client.database_version()
+        {
+            Some( x )   => x
+        ,   None        =>
             {
-                Ok( x ) =>
+                if self.cache.mode() != cache::Mode::Off
                 {
-                    if c_name == "" || x.flds.iter().find(|&x| x.0 == "directory" && x.1 == c_name ) != None
-                    {
-                        self.curdir = String::from( "/" ) + &dir;
-                    }
-                    else
+                    if let Ok( x ) = self.client.stats()
                     {
-                        eprint!( "No such directory" );
+                        self.cache.check_stamp( x.db_update );
                     }
                 }
-            ,   Err( x ) => self.show_error( &x )
+
+                0
             }
+        };
+
+        if let Some( x ) = self.cache.get( &cmd, version )
+        {
+            return x;
         }
-    }
 
-    fn cmd_pl( &mut self )
+        if self.cache.needs_stamp()
+        {
+            if let Ok( x ) = self.client.stats()
+            {
+                self.cache.set_stamp( x.db_update );
+            }
+        }
+
+        let ret = self.client.exec_command( &cmd ).map( |x| ListEntry::list_from_fields( x.flds ) );
+
+        self.cache.put( cmd, &ret );
+
+        ret
+    }
+
+    /// Reads the cache file of the server ( `cache = "disk"` ), if it belongs to the database MPD has now.
+    fn load_cache( &self )
+    {
+        if !self.cache.needs_stamp()
+        {
+            return;
+        }
+
+        if let Ok( x ) = self.client.stats()
+        {
+            if let Err( x ) = self.cache.load( x.db_update )
+            {
+                self.show_cache_error( &x );
+            }
+        }
+    }
+
+    /// Writes the cache file of the server ( `cache = "disk"` ).
+    fn save_cache( &self )
+    {
+        if let Err( x ) = self.cache.save()
+        {
+            self.show_cache_error( &x );
+        }
+    }
+
+    /// The cache file is not needed to go on, so this does not fail the command.
+    fn show_cache_error( &self, err : &io::Error )
+    {
+        eprintln!( "Cache Error... {} ({})", self.cache.path().map( |x| x.display().to_string() ).unwrap_or_default(), err );
+    }
+
+    /// cd [DIR]. `-` is the previous directory, `@` the directory of the current song.
+    fn cmd_cd( &mut self )
+    {
+        if self.args.len() >= 2
+        {
+            let arg = self.args[1].clone();
+
+            if let Some( x ) = self.resolve_dir( &arg )
+            {
+                if self.change_dir( &x ) && arg == "-"
+                {
+                    outln!( self, "{}", self.curdir );
+                }
+            }
+        }
+    }
+
+    /// `-` and `@` as a directory. The other names as they are. None if it failed.
+    fn resolve_dir( &self, arg : &str ) -> Option<String>
+    {
+        match arg
+        {
+            "-" =>
+            {
+                if self.prevdir.is_none()
+                {
+                    self.show_fail( "No previous directory..." );
+                }
+
+                self.prevdir.clone()
+            }
+        ,   "@" =>
+            {
+                match self.client.current_song()
+                {
+                    Ok( Some( x ) ) => Some( format!( "/{}", Self::make_parent_path( &x.file ).0 ) )
+                ,   Ok( None )      => { self.show_fail( "No current song..." ); None }
+                ,   Err( x )        => { self.show_error( &x ); None }
+                }
+            }
+        ,   _ => Some( String::from( arg ) )
+        }
+    }
+
+    /// Changes to `arg` if it is a directory. Returns false if it failed.
+    fn change_dir( &mut self, arg : &str ) -> bool
     {
-        let mut songid_cur  : Option< String > = None;
-        let mut songid_next : Option< String > = None;
+        let dir = self.make_path( arg );
 
-        match self.exec_command( "status" )
+        let ( p_dir, c_name ) = Self::make_parent_path( &dir );
+
+        match self.list_dir( &p_dir, false )
         {
             Ok( x ) =>
             {
-                for( k, v ) in x.flds
+                let is_root = p_dir.is_empty() && vdir::is_root( &c_name );
+
+                if c_name.is_empty() || is_root || x.iter().any( |x| x.kind == EntryKind::Directory && x.base_name() == c_name )
                 {
-                    if k == "songid"
-                    {
-                        songid_cur = Some( v );
-                    }
-                    else if k == "nextsongid"
+                    let prev = std::mem::replace( &mut self.curdir, String::from( "/" ) + &dir );
+
+                    if prev != self.curdir
                     {
-                        songid_next = Some( v );
+                        self.prevdir = Some( prev );
                     }
+
+                    return true;
                 }
+
+                self.show_fail( "No such directory" );
+            }
+        ,   Err( x ) => self.show_error( &x )
+        }
+
+        false
+    }
+
+    /// pushd [DIR]. Without DIR, swaps the current directory and the top of the stack.
+    fn cmd_pushd( &mut self )
+    {
+        let prev = self.curdir.clone();
+
+        let dir = if self.args.len() >= 2
+        {
+            let arg = self.args[1].clone();
+
+            match self.resolve_dir( &arg )
+            {
+                Some( x )   => x
+            ,   None        => return
+            }
+        }
+        else
+        {
+            match self.dirstack.pop()
+            {
+                Some( x )   => x
+            ,   None        => { self.show_fail( "The directory stack is empty..." ); return; }
             }
-        ,   Err(_) => {}
         };
 
-        match self.exec_command( "playlistinfo" )
+        if self.change_dir( &dir )
         {
-            Ok(x) =>
+            self.dirstack.push( prev );
+            self.cmd_dirs();
+        }
+        else if self.args.len() < 2
+        {
+            self.dirstack.push( dir );
+        }
+    }
+
+    fn cmd_popd( &mut self )
+    {
+        match self.dirstack.pop()
+        {
+            Some( x ) =>
             {
-                let mut pos = 0;
+                if self.change_dir( &x )
+                {
+                    self.cmd_dirs();
+                }
+            }
+        ,   None => self.show_fail( "The directory stack is empty..." )
+        }
+    }
+
+    /// The current directory, then the stack from the top.
+    fn cmd_dirs( &self )
+    {
+        for ( i, x ) in std::iter::once( &self.curdir ).chain( self.dirstack.iter().rev() ).enumerate()
+        {
+            outln!( self, "{:2}  {}", i, x );
+        }
+    }
+
+    /// mark [NAME [DIR]]. Bookmarks DIR ( the current directory ) as NAME, for `cd :NAME`.
+    fn cmd_mark( &mut self )
+    {
+        if self.args.len() < 2
+        {
+            for ( name, dir ) in &self.bookmarks
+            {
+                outln!( self, "{:12} {}", format!( ":{}", name ), dir );
+            }
+
+            return;
+        }
+
+        let name = self.args[1].trim_start_matches( ':' ).to_string();
+
+        if name.is_empty() || name.contains( '/' )
+        {
+            self.show_fail( &format!( "Bad bookmark name... {}", self.args[1] ) );
+            return;
+        }
 
-                for entry in Self::split_listfiles( x.flds )
+        let dir = match self.args.get( 2 ).cloned()
+        {
+            Some( x ) => match self.resolve_dir( &x )
                 {
-                    let mut flg = "";
+                    Some( x )   => String::from( "/" ) + &self.make_path( &x )
+                ,   None        => return
+                }
+        ,   None => self.curdir.clone()
+        };
 
-                    for ( k, v ) in &entry.flds
-                    {
-                        if k == "Id"
-                        {
-                            if songid_cur.is_some() && songid_cur.as_ref().unwrap() == v
-                            {
-                                flg = "=>"
-                            }
-                            else if songid_next.is_some() && songid_next.as_ref().unwrap() == v
-                            {
-                                flg = "."
-                            }
+        if self.save_config
+        {
+            if let Err( x ) = config::Config::save_bookmark( &name, Some( &dir ) )
+            {
+                self.show_fail( &format!( "Config Error... {}", x ) );
+            }
+        }
 
-                            break;
-                        }
+        self.bookmarks.insert( name, dir );
+    }
+
+    fn cmd_unmark( &mut self )
+    {
+        let names : Vec<String> = self.args.iter().skip( 1 ).map( |x| x.trim_start_matches( ':' ).to_string() ).collect();
+
+        for name in names
+        {
+            if self.bookmarks.remove( &name ).is_none()
+            {
+                self.show_fail( &format!( "No such bookmark... {}", name ) );
+                continue;
+            }
+
+            if self.save_config
+            {
+                if let Err( x ) = config::Config::save_bookmark( &name, None )
+                {
+                    self.show_fail( &format!( "Config Error... {}", x ) );
+                }
+            }
+        }
+    }
+
+    fn cmd_pl( &mut self )
+    {
+        let ( songid_cur, songid_next ) = match self.client.status()
+        {
+            Ok( x ) => ( x.song_id, x.next_song_id )
+        ,   Err(_)  => ( None, None )
+        };
+
+        match self.client.queue()
+        {
+            Ok(x) if self.captured() =>
+            {
+                for song in x
+                {
+                    outln!( self, "/{}", song.file );
+                }
+            }
+        ,   Ok(x) =>
+            {
+                let mut pos = 0;
+
+                for song in x
+                {
+                    let flg = if song.id.is_some() && song.id == songid_cur
+                    {
+                        "=>"
                     }
+                    else if song.id.is_some() && song.id == songid_next
+                    {
+                        "."
+                    }
+                    else
+                    {
+                        ""
+                    };
 
-                    if self.has_opt( "-l" ) && !entry.flds.is_empty() || pos == 0
+                    if self.has_opt( 'l' ) && !song.flds.is_empty() || pos == 0
                     {
-                        println!( "" );
+                        outln!( self );
                     }
 
-                    println!( "{:2}{:4}| {:9}: {}", flg, pos, entry.name_type, entry.name );
+                    outln!( self, "{:2}{:4}| {:9}: {}", flg, pos, EntryKind::File, song.file );
 
-                    if self.has_opt( "-l" )
+                    if self.has_opt( 'l' )
                     {
-                        for ( k, mut v ) in entry.flds
+                        for ( k, mut v ) in song.flds
                         {
                             if k == "duration"
                             {
@@ -623,7 +907,7 @@ impl Mpdsh
                                 };
                             }
 
-                            println!( "      | {:9}: {}", k, v );
+                            outln!( self, "      | {:9}: {}", k, v );
                         }
                     }
 
@@ -632,73 +916,75 @@ impl Mpdsh
 
                 if pos == 0
                 {
-                    println!( "No files ..." );
+                    outln!( self, "No files ..." );
                 }
                 else
                 {
-                    println!( "" );
+                    outln!( self );
                 }
             }
         ,   Err(x) => self.show_error( &x )
         }
     }
 
-    fn hint_playlist( &mut self ) -> Vec<String>
+    /// Playlist positions starting with `partial`, or the positions of the songs whose title or file name contains it.
+    /// With `pattern`, the file names starting with `partial` ( for `del <PATTERN>` ).
+    fn hint_queue( &self, partial : &str, pattern : bool ) -> Vec<String>
     {
-        match self.exec_command( "playlistinfo" )
+        let songs = match self.client.queue()
         {
-            Ok( x ) =>
-            {
-                let mut ret = Vec::<String>::new();
+            Ok( x )     => x
+        ,   Err(_)      => return Vec::<String>::new()
+        };
 
-                for ( _, nm ) in x.flds.iter().filter(|&x| x.0 == "file" )
-                {
-                    let mut val = nm.clone();
+        if partial.chars().all( |x| x.is_ascii_digit() )
+        {
+            return ( 0 .. songs.len() ).map( |x| x.to_string() ).filter( |x| x.starts_with( partial ) ).collect();
+        }
 
-                    if val.contains( ' ' )
-                    {
-                        val = String::from( "\"" ) + &val + "\"";
-                    }
+        let lower = partial.to_lowercase();
+        let mut ret = Vec::<String>::new();
 
-                    ret.push( val );
-                }
+        for ( pos, song ) in songs.iter().enumerate()
+        {
+            let ( _p_dir, c_name ) = Self::make_parent_path( &song.file );
 
-                return ret;
+            if pattern
+            {
+                if c_name.to_lowercase().starts_with( &lower ) && !ret.contains( &c_name )
+                {
+                    ret.push( c_name );
+                }
+            }
+            else if c_name.to_lowercase().contains( &lower ) || song.title().is_some_and( |x| x.to_lowercase().contains( &lower ) )
+            {
+                ret.push( pos.to_string() );
             }
-        ,   Err(_x) => {}
         }
 
-        return Vec::<String>::new();
+        ret.into_iter().map( Self::hint_quote ).collect()
     }
 
     fn cmd_ls( &mut self )
     {
-        let cmd_add     = self.args[0] == "add"         || self.args[0] == "a"  ;
-        let cmd_add_top = self.args[0] == "add_top"     || self.args[0] == "at" ;
-
-        let mut dir;
-        let mut wmatch = None;
+        let cmd_add     = self.args[0] == "add";
+        let cmd_add_top = self.args[0] == "add_top";
+        let cmd_pl_add  = self.args[0] == "playlistadd";
 
-        if self.args.len() >= 2
-        {
-            dir = self.get_arge1_path();
-            dir = Self::make_canonical_path( &dir );
+        // playlistadd takes the playlist name first
+        let first = if cmd_pl_add { 2 } else { 1 };
 
-            let ( p_dir, c_name ) = Self::make_parent_path( &dir );
+        let ( dir, pattern ) = self.get_arg_dir( first );
 
-            if c_name.contains( '*' ) || c_name.contains( '?' )
-            {
-                dir = p_dir;
-                wmatch = Some( c_name );
-            }
-        }
-        else
+        if self.has_opt( 'R' )
         {
-            dir = String::from( &self.curdir );
-            dir = Self::make_canonical_path( &dir );
-        };
+            self.ls_recursive( &dir, pattern.as_ref() );
+            return;
+        }
 
-        let cmd = if self.has_opt( "-l" ) || cmd_add || cmd_add_top
+        let adding = cmd_add || cmd_add_top || cmd_pl_add;
+
+        let cmd = if self.has_opt( 'l' ) || adding
         {
             "lsinfo"
         }
@@ -707,116 +993,99 @@ impl Mpdsh
             "listfiles"
         };
 
-        match self.exec_command( &Self::make_command_1( cmd, &dir ) )
+        // wildcards across directories, and the directories matched by add, are expanded with listall
+        let entries = match &pattern
         {
-            Ok( x ) =>
+            Some( x ) if ( adding || !x.is_shallow() ) && vdir::VirtualDir::parse( &dir ).is_none() =>
             {
-                if cmd == "lsinfo"
+                self.glob( &dir, x, cmd == "lsinfo", adding )
+            }
+        ,   Some( x ) => self.list_dir( &dir, cmd == "lsinfo" ).map( |mut tmp|
                 {
-                    let mut tmp = Self::split_listfiles( x.flds );
-
-                    if wmatch.is_some()
-                    {
-                        let wmatch_ptn = wildmatch::WildMatch::new( &wmatch.unwrap() );
+                    tmp.retain( |e| x.matches_name( e.base_name() ) );
+                    tmp
+                }
+            )
+        ,   None => self.list_dir( &dir, cmd == "lsinfo" )
+        };
 
-                        let mut tmp2 = Vec::< ListEntry >::new();
+        match entries
+        {
+            Ok( mut tmp ) =>
+            {
 
-                        for entry in tmp
+                if cmd == "lsinfo"
+                {
+                    if cmd_add || cmd_add_top || cmd_pl_add
+                    {
+                        // the directories of the virtual tree add their songs
+                        if vdir::VirtualDir::parse( &dir ).is_some()
                         {
-                            let ( _p_dir, c_name ) = Self::make_parent_path( &entry.name );
+                            let mut songs = Vec::< ListEntry >::new();
 
-                            if wmatch_ptn.is_match( &c_name )
+                            for entry in tmp
                             {
-                                tmp2.push( entry );
+                                match vdir::VirtualDir::parse( &Self::entry_path( &dir, &entry.name ) )
+                                {
+                                    Some( x ) if entry.kind == EntryKind::Directory =>
+                                    {
+                                        match x.songs( &self.client )
+                                        {
+                                            Ok( x )     => songs.extend( x )
+                                        ,   Err( x )    => { self.show_error( &x ); return; }
+                                        }
+                                    }
+                                ,   _ => songs.push( entry )
+                                }
                             }
+
+                            tmp = songs;
                         }
 
-                        tmp = tmp2;
-                    }
+                        let targets : Vec< ListEntry > = tmp.into_iter().filter( |x| x.kind == EntryKind::File || x.kind == EntryKind::Playlist && !cmd_pl_add ).collect();
 
-                    let mut pos = 0;
+                        let name = if cmd_pl_add { self.playlist_name( &self.args[1] ) } else { String::new() };
 
-                    for entry in tmp
-                    {
-                        if cmd_add || cmd_add_top
-                        {
-                            if entry.name_type == "file" || entry.name_type == "playlist"
+                        let cmds : Vec< String > = targets.iter().enumerate().map( |( pos, entry )|
                             {
-                                let cmd = if cmd_add_top
+                                if cmd_add_top
                                 {
-                                    String::from( &Self::make_command_2( "addid", &entry.name, &pos.to_string() ) )
+                                    make_command_2( "addid", &entry.name, &pos.to_string() )
                                 }
-                                else
+                                else if cmd_pl_add
                                 {
-                                    String::from( &Self::make_command_1( "add", &entry.name ) )
-                                };
-
-                                match self.exec_command( &cmd )
-                                {
-                                    Ok(_) =>
-                                    {
-                                        println!( " A {:9}: {}", entry.name_type, entry.name );
-                                        pos += 1;
-                                    }
-                                ,   Err(x) =>
-                                    {
-                                        self.show_error( &x );
-                                        break
-                                    }
+                                    make_command_2( "playlistadd", &name, &entry.name )
                                 }
-                            }
-                        }
-                        else
-                        {
-                            if !entry.flds.is_empty() || pos == 0
-                            {
-                                println!( "" );
-                            }
-
-                            println!( "{:12}: {}", entry.name_type, entry.name );
-
-                            for ( k, mut v ) in entry.flds
-                            {
-                                if k == "duration"
+                                else
                                 {
-                                    if let Ok(x) = Self::format_duration( &v )
-                                    {
-                                        v = x;
-                                    };
+                                    make_command_1( "add", &entry.name )
                                 }
-
-                                println!( " | {:9}: {}", k, v );
                             }
+                        ).collect();
 
-                            pos += 1;
-                        }
-                    }
+                        self.exec_add( &targets, &cmds );
 
-                    if ( cmd_add || cmd_add_top ) && pos == 0
-                    {
-                        println!( "No files added..." );
+                        if cmd_pl_add
+                        {
+                            self.cache.clear();
+                        }
                     }
-                    else if pos != 0
+                    else
                     {
-                        println!( "" );
+                        self.print_entries_long( tmp );
                     }
                 }
                 else
                 {
-                    let mut tmp : Vec<&(String,String)> = x.flds.iter().filter( |&x| x.0 == "directory" || x.0 == "file" ).collect();
-
-                    if wmatch.is_some()
-                    {
-                        let wmatch_ptn = wildmatch::WildMatch::new( &wmatch.unwrap() );
-
-                        tmp = tmp.iter().map( |x| *x ).filter( |&x| wmatch_ptn.is_match( &x.1 ) ).collect();
-                    }
-
-                    for ( tp, nm ) in tmp
+                    for entry in tmp.iter().filter( |x| x.kind != EntryKind::Playlist )
                     {
-                        if !cmd_add
+                        if self.captured()
+                        {
+                            outln!( self, "/{}", Self::entry_path( &dir, &entry.name ) );
+                        }
+                        else
                         {
-                            println!( "{:12}: {}", tp, nm );
+                            outln!( self, "{:12}: {}", entry.kind, entry.name );
                         }
                     }
                 }
@@ -825,156 +1094,975 @@ impl Mpdsh
         }
     }
 
-    fn hint_entry( &mut self, with_file : bool ) -> ( Vec<String>, usize )
+    /// The directory named by the argument at `index` ( the current directory if missing ),
+    /// and the pattern if it has wildcards. The directory is then the part before the first wildcard.
+    fn get_arg_dir( &self, index : usize ) -> ( String, Option< glob::Pattern > )
     {
-        let mut dir;
-        let mut wmatch = None;
+        if self.args.len() <= index
+        {
+            return ( Self::make_canonical_path( &self.curdir ), None );
+        }
+
+        let path = self.get_arg_path( index );
+
+        if glob::has_magic( &path ) && !self.exists( &path )
+        {
+            let pattern = glob::Pattern::new( &path );
+
+            ( String::from( pattern.base() ), Some( pattern ) )
+        }
+        else
+        {
+            ( path, None )
+        }
+    }
+
+    /// True if `path` is an entry of the database, so a name like `Album [Deluxe]` is not taken as a pattern.
+    fn exists( &self, path : &str ) -> bool
+    {
+        let ( p_dir, c_name ) = Self::make_parent_path( path );
+
+        c_name.is_empty() || self.list_dir( &p_dir, false ).is_ok_and( |x| x.iter().any( |e| e.base_name() == c_name ) )
+    }
+
+    /// The entries under `dir` matching `pattern`, from listall ( listallinfo for `info` ).
+    /// With `within`, the files in the matching directories instead.
+    fn glob( &self, dir : &str, pattern : &glob::Pattern, info : bool, within : bool ) -> Result< Vec< ListEntry >, Ack >
+    {
+        let entries = self.list_cached( if info { "listallinfo" } else { "listall" }, dir )?;
+
+        Ok( entries.into_iter().filter( |x|
+            {
+                if within
+                {
+                    x.kind != EntryKind::Directory && pattern.matches_within( &x.name )
+                }
+                else
+                {
+                    pattern.matches( &x.name )
+                }
+            }
+        ).collect() )
+    }
+
+    /// The tree under `dir` from listall ( listallinfo for `info` ), with the files matching `pattern`.
+    /// A pattern with wildcards in the last part only matches at any depth, as `find -name`.
+    /// A virtual directory gives the tree of the real directories of its songs.
+    fn walk( &self, dir : &str, pattern : Option< &glob::Pattern >, info : bool ) -> Result< tree::Node, Ack >
+    {
+        if let Some( x ) = vdir::VirtualDir::parse( dir )
+        {
+            let mut songs = x.songs( &self.client )?;
+
+            if let Some( x ) = pattern
+            {
+                songs.retain( |e| x.matches_name( e.base_name() ) );
+            }
+
+            return Ok( tree::Node::build( "", songs, None ) );
+        }
+
+        let filter = pattern.map( |x| if x.is_shallow() { x.anywhere() } else { x.clone() } );
+
+        let entries = self.list_cached( if info { "listallinfo" } else { "listall" }, dir )?;
+
+        Ok( tree::Node::build( dir, entries, filter.as_ref() ) )
+    }
+
+    /// `3 files, 00:12:34`
+    fn format_totals( node : &tree::Node ) -> String
+    {
+        let duration = Self::format_duration( &node.duration.to_string() ).unwrap_or_default();
+
+        format!( "{} file{}, {}", node.count, if node.count == 1 { "" } else { "s" }, duration )
+    }
+
+    /// ls -R. Each directory with its totals, then its entries. `$(ls -R)` has the files only.
+    fn ls_recursive( &self, dir : &str, pattern : Option< &glob::Pattern > )
+    {
+        let long = self.has_opt( 'l' );
+
+        match self.walk( dir, pattern, !self.captured() )
+        {
+            Ok( x ) if self.captured()  => self.print_files( &x )
+        ,   Ok( x )                     => self.print_ls_recursive( &x, long )
+        ,   Err( x )                    => self.show_error( &x )
+        }
+    }
+
+    fn print_files( &self, node : &tree::Node )
+    {
+        for x in node.dirs.values()
+        {
+            self.print_files( x );
+        }
+
+        for x in &node.files
+        {
+            outln!( self, "/{}", x.name );
+        }
+    }
+
+    fn print_ls_recursive( &self, node : &tree::Node, long : bool )
+    {
+        outln!( self, "/{}: {}", node.path, Self::format_totals( node ) );
+
+        for x in node.dirs.values()
+        {
+            outln!( self, "{:12}: {}", EntryKind::Directory, x.path );
+        }
+
+        if long && !node.files.is_empty()
+        {
+            self.print_entries_long( node.files.clone() );
+        }
+        else
+        {
+            for x in &node.files
+            {
+                outln!( self, "{:12}: {}", x.kind, x.name );
+            }
+
+            outln!( self );
+        }
+
+        for x in node.dirs.values()
+        {
+            self.print_ls_recursive( x, long );
+        }
+    }
+
+    /// tree [-d DEPTH] [DIR]. The directories with their totals, down to DEPTH levels.
+    fn cmd_tree( &mut self )
+    {
+        let depth = match self.opt_value( 'd' ).map( usize::from_str )
+        {
+            Some( Ok( 0 ) ) | Some( Err(_) )    => { self.show_fail( "Please specify the depth as a number from 1..." ); return; }
+        ,   Some( Ok( x ) )                     => Some( x )
+        ,   None                                => None
+        };
+
+        let ( dir, pattern ) = self.get_arg_dir( 1 );
+
+        match self.walk( &dir, pattern.as_ref(), true )
+        {
+            Ok( x ) =>
+            {
+                outln!( self, "/{}  ( {} )", x.path, Self::format_totals( &x ) );
+
+                self.print_tree( &x, "", depth );
+
+                outln!( self );
+                outln!( self, "{} director{}, {}", x.dir_count(), if x.dir_count() == 1 { "y" } else { "ies" }, Self::format_totals( &x ) );
+            }
+        ,   Err( x ) => self.show_error( &x )
+        }
+    }
+
+    fn print_tree( &self, node : &tree::Node, prefix : &str, depth : Option<usize> )
+    {
+        if depth == Some( 0 )
+        {
+            return;
+        }
+
+        let count = node.dirs.len() + node.files.len();
+
+        for ( i, x ) in node.dirs.values().enumerate()
+        {
+            let last = i + 1 == count;
+
+            outln!( self, "{}{}{}/  ( {} )", prefix, if last { "`-- " } else { "|-- " }, x.name(), Self::format_totals( x ) );
+
+            self.print_tree( x, &format!( "{}{}", prefix, if last { "    " } else { "|   " } ), depth.map( |d| d - 1 ) );
+        }
+
+        for ( i, x ) in node.files.iter().enumerate()
+        {
+            let last = node.dirs.len() + i + 1 == count;
+
+            outln!( self, "{}{}{}", prefix, if last { "`-- " } else { "|-- " }, x.base_name() );
+        }
+    }
+
+    /// Runs the commands adding `targets` in a batch, and prints what was added.
+    fn exec_add( &self, targets : &[ ListEntry ], cmds : &[ String ] )
+    {
+        let ( done, err ) = match self.client.exec_command_batch( cmds, true )
+        {
+            Ok(_)               => ( targets.len(), None )
+        ,   Err( ( idx, x ) )   => ( idx, Some( x ) )
+        };
+
+        for entry in &targets[ .. done ]
+        {
+            outln!( self, " A {:9}: {}", entry.kind, entry.name );
+        }
+
+        if let Some( x ) = err
+        {
+            if let Some( entry ) = targets.get( done )
+            {
+                outln!( self, " ! {:9}: {}", entry.kind, entry.name );
+            }
+
+            self.show_error( &x );
+        }
+        else if done == 0
+        {
+            outln!( self, "No files added..." );
+        }
+        else
+        {
+            outln!( self );
+        }
+    }
+
+    /// Prints the entries with their fields, as `ls -l`.
+    fn print_entries_long( &self, entries : Vec< ListEntry > )
+    {
+        let mut pos = 0;
+
+        for entry in entries
+        {
+            if !entry.flds.is_empty() || pos == 0
+            {
+                outln!( self );
+            }
+
+            outln!( self, "{:12}: {}", entry.kind, entry.name );
+
+            for ( k, mut v ) in entry.flds
+            {
+                if k == "duration"
+                {
+                    if let Ok(x) = Self::format_duration( &v )
+                    {
+                        v = x;
+                    };
+                }
+
+                outln!( self, " | {:9}: {}", k, v );
+            }
+
+            pos += 1;
+        }
+
+        if pos != 0
+        {
+            outln!( self );
+        }
+    }
+
+    /// listfiles returns names relative to `dir`, lsinfo and find return whole paths.
+    fn entry_path( dir : &str, name : &str ) -> String
+    {
+        if dir.is_empty() || name.contains( '/' )
+        {
+            String::from( name )
+        }
+        else
+        {
+            format!( "{}/{}", dir, name )
+        }
+    }
+
+    /// Runs `f` once for each argument, as if each was given alone. add_top runs them in reverse to keep the order.
+    /// The playlist name of playlistadd is given to each.
+    fn cmd_each_arg( &mut self, f : fn( &mut Self ) )
+    {
+        let fixed = if self.args[0] == "playlistadd" { 2 } else { 1 };
+
+        if self.args.len() <= fixed + 1
+        {
+            f( self );
+            return;
+        }
+
+        let args = self.args.clone();
+
+        let mut targets : Vec<String> = args[ fixed .. ].to_vec();
+
+        if args[0] == "add_top"
+        {
+            targets.reverse();
+        }
+
+        for x in targets
+        {
+            self.args = args[ .. fixed ].to_vec();
+            self.args.push( x );
+
+            f( self );
+
+            if self.failed()
+            {
+                break;
+            }
+        }
+
+        self.args = args;
+    }
+
+    /// find and search ( ignoring case ). The terms are translated by `filter::parse`.
+    /// `-a` adds the songs with findadd / searchadd instead of listing them.
+    fn cmd_find( &mut self, search : bool )
+    {
+        let cmd1 = if search { "search" } else { "find" };
+
+        let query = match filter::parse( &self.args[ 1 .. ], &self.client.tagtypes().unwrap_or_default() )
+        {
+            Ok( x )     => x
+        ,   Err( x )    => { self.show_fail( &x ); return; }
+        };
+
+        let window = self.opt_value( 'w' ).map( String::from );
+
+        if window.as_ref().is_some_and( |x| !Self::is_pos_or_range( x ) || !x.contains( ':' ) )
+        {
+            self.show_fail( "Please specify the window as START:END..." );
+            return;
+        }
+
+        let mut args = vec![ query.expr.clone() ];
+
+        if let Some( x ) = self.opt_value( 's' )
+        {
+            args.push( String::from( "sort" ) );
+            args.push( String::from( x ) );
+        }
+
+        // MPD can not compare, the window is cut here after the checks
+        if query.checks.is_empty()
+        {
+            if let Some( x ) = &window
+            {
+                args.push( String::from( "window" ) );
+                args.push( x.clone() );
+            }
+
+            if self.has_opt( 'a' )
+            {
+                let cmd = args.iter().fold( format!( "{}add", cmd1 ), |cmd, x| cmd + " " + &quote_arg( x ) );
+
+                match self.client.exec_command( &cmd )
+                {
+                    Ok(_)       => outln!( self, "OK." )
+                ,   Err( x )    => self.show_error( &x )
+                }
+
+                return;
+            }
+        }
+
+        let songs = if search { self.client.search( &args ) } else { self.client.find( &args ) };
+
+        let mut songs = match songs
+        {
+            Ok( x )     => x
+        ,   Err( x )    => { self.show_error( &x ); return; }
+        };
+
+        if !query.checks.is_empty()
+        {
+            songs.retain( |x| query.matches( x ) );
+
+            if let Some( ( start, end ) ) = window.as_ref().and_then( |x| x.split_once( ':' ) )
+            {
+                let start   = start.parse::<usize>().unwrap_or( 0 ).min( songs.len() );
+                let end     = end.parse::<usize>().unwrap_or( songs.len() ).clamp( start, songs.len() );
+
+                songs = songs.drain( start .. end ).collect();
+            }
+        }
+
+        let entries : Vec< ListEntry > = songs.into_iter().map( |x| ListEntry { name: x.file, kind: EntryKind::File, flds: x.flds } ).collect();
+
+        if self.has_opt( 'a' )
+        {
+            let cmds : Vec< String > = entries.iter().map( |x| make_command_1( "add", &x.name ) ).collect();
+
+            self.exec_add( &entries, &cmds );
+        }
+        else if self.captured()
+        {
+            for entry in entries
+            {
+                outln!( self, "/{}", entry.name );
+            }
+        }
+        else if entries.is_empty()
+        {
+            outln!( self, "No files ..." );
+        }
+        else
+        {
+            self.print_entries_long( entries );
+        }
+    }
+
+    /// A tag name, or after `TAG<OP>` a value of the tag. After a bare tag ( `find artist <TAB>` ), a value.
+    fn hint_filter( &self, prev : &str, partial : &str ) -> ( Vec<String>, usize )
+    {
+        let tags = self.client.tagtypes().unwrap_or_default();
+
+        let values = | tag : &str, partial : &str | match tag.to_lowercase().as_str()
+        {
+            "any" | "file" | "base" => ( Vec::<String>::new(), 0 )
+        ,   _                       => Self::hint_names( self.client.list_tag( tag, None ).unwrap_or_default(), partial )
+        };
+
+        if let Some( ( tag, _, val ) ) = filter::split_term( partial )
+        {
+            return values( tag, val );
+        }
+
+        if tags.iter().any( |x| x.eq_ignore_ascii_case( prev ) )
+        {
+            return values( prev, partial );
+        }
+
+        let mut names = vec![ String::from( "any" ), String::from( "file" ), String::from( "base" ) ];
+
+        names.extend( tags );
+
+        Self::hint_names( names, partial )
+    }
+
+    fn hint_entry( &mut self, with_file : bool ) -> ( Vec<String>, usize )
+    {
+        let mut dir;
+        let mut wmatch = None;
 
         if self.args.len() >= 2
         {
-            dir = self.get_arge1_path();
-            dir = Self::make_canonical_path( &dir );
+            dir = self.get_arge1_path();
+            dir = Self::make_canonical_path( &dir );
+
+            let ( p_dir, c_name ) = Self::make_parent_path( &dir );
+
+            if glob::has_magic( &c_name )
+            {
+                dir = p_dir;
+                wmatch = Some( c_name );
+            }
+        }
+        else
+        {
+            dir = String::from( &self.curdir );
+            dir = Self::make_canonical_path( &dir );
+        };
+
+        let is_target = |x: &ListEntry| x.kind == EntryKind::Directory || with_file && x.kind == EntryKind::File;
+
+        // the virtual roots are hidden from ls, but completed
+        let list = | sh : &Self, dir : &str | sh.list_dir( dir, false ).map( |mut x|
+            {
+                if dir.is_empty()
+                {
+                    x.extend( vdir::roots() );
+                }
+
+                x
+            }
+        );
+
+        match list( self, &dir )
+        {
+            Ok( x ) =>
+            {
+                let mut tmp : Vec< ListEntry > = x.into_iter().filter( is_target ).collect();
+                let mut posd = 0;
+
+                if let Some( c_name ) = wmatch
+                {
+                    let pattern = glob::literal_first( &c_name, true, |p| tmp.iter().any( |x| p.matches_name( x.base_name() ) ) );
+
+                    tmp.retain( |x| pattern.matches_name( x.base_name() ) );
+
+                    posd = c_name.len();
+                }
+
+                let mut ret = Vec::<String>::new();
+
+                for entry in tmp
+                {
+                    ret.push( Self::hint_quote( String::from( entry.base_name() ) ) );
+                }
+
+                return ( ret, posd );
+            }
+        ,   Err( x ) => {
+                if x.code == AckCode::NoExist
+                {
+                    let ( p_dir, c_name ) = Self::make_parent_path( &dir );
+
+                    if let Ok( x ) = list( self, &p_dir )
+                    {
+                        let mut ret = Vec::<String>::new();
+
+                        for entry in x.into_iter().filter( is_target )
+                        {
+                            if entry.base_name().starts_with( &c_name )
+                            {
+                                ret.push( Self::hint_quote( String::from( entry.base_name() ) ) );
+                            }
+                        }
+
+                        return ( ret, c_name.len() );
+                    }
+                }
+            }
+        }
+
+        ( Vec::<String>::new(), 0 )
+    }
+
+    /// update [DIR]. The cached listings are dropped now, and again when MPD is done ( idle `update` / `database` ).
+    fn cmd_update( &self )
+    {
+        self.cmd_with_args( "update", 1 );
+        self.cache.clear();
+    }
+
+    fn cmd_with_args( &self, cmd1 : &str, num_args : usize )
+    {
+        let mut cmd = String::new();
+
+        cmd.push_str( cmd1 );
+
+        for x in self.args.iter().skip(1).take( num_args )
+        {
+            cmd.push(' ');
+            cmd.push_str( &quote_arg( x ) );
+        }
+
+        match self.client.exec_command( &cmd )
+        {
+            Ok(_) =>
+            {
+                outln!( self, "OK." );
+            }
+        ,   Err(x) => self.show_error( &x )
+        }
+    }
+
+    /// cmd_with_args for the commands on stored playlists. The first `names` arguments are playlist names.
+    fn cmd_playlist( &mut self, cmd1 : &str, names : usize, num_args : usize )
+    {
+        for i in 1 .. self.args.len().min( names + 1 )
+        {
+            self.args[ i ] = self.playlist_name( &self.args[ i ] );
+        }
+
+        self.cmd_with_args( cmd1, num_args );
+
+        // the playlists are in the listings, and a script has no idle listener to drop them
+        if cmd1 != "load"
+        {
+            self.cache.clear();
+        }
+    }
 
-            let ( p_dir, c_name ) = Self::make_parent_path( &dir );
+    fn is_pos_or_range( arg : &str ) -> bool
+    {
+        lazy_static! {
+            static ref RE: regex::Regex =
+                regex::Regex::new( r"^\d+(:\d*)?$" ).unwrap();
+        }
+
+        RE.is_match( arg )
+    }
+
+    /// Deletes each argument. Positions are deleted from the end of the playlist, so the others do not shift.
+    fn cmd_del( &mut self )
+    {
+        let neg = | x : &String | x.strip_prefix( '-' ).and_then( |x| x.parse::<u32>().ok() );
 
-            if c_name.contains( '*' ) || c_name.contains( '?' )
+        // -N counts from the end of the playlist
+        if self.args.iter().skip( 1 ).any( |x| neg( x ).is_some() )
+        {
+            let len = match self.client.status()
             {
-                dir = p_dir;
-                wmatch = Some( c_name );
+                Ok( x )     => x.playlist_length
+            ,   Err( x )    => { self.show_error( &x ); return; }
+            };
+
+            for x in self.args.iter_mut().skip( 1 )
+            {
+                match neg( x )
+                {
+                    Some( n ) if n >= 1 && n <= len => { *x = ( len - n ).to_string(); }
+                ,   Some(_) =>
+                    {
+                        let msg = format!( "No such position... {}", x );
+                        self.show_fail( &msg );
+                        return;
+                    }
+                ,   None    => {}
+                }
             }
         }
-        else
+
+        let start = | x : &String | if Self::is_pos_or_range( x ) { x.split( ':' ).next().and_then( |x| x.parse::<u32>().ok() ) } else { None };
+
+        // patterns go last, they delete by id
+        self.args[ 1 .. ].sort_by_key( |x| std::cmp::Reverse( start( x ) ) );
+
+        self.cmd_each_arg( Self::cmd_del_one );
+    }
+
+    fn cmd_del_one( &mut self )
+    {
+        if Self::is_pos_or_range( &self.args[1] )
         {
-            dir = String::from( &self.curdir );
-            dir = Self::make_canonical_path( &dir );
-        };
+            self.cmd_with_args( "delete", 1 );
+            return;
+        }
+
+        // a pattern with a directory matches the paths, and the songs under a matching directory
+        let by_path = self.args[1].contains( '/' );
 
-        match self.exec_command( &Self::make_command_1( "listfiles", &dir ) )
+        match self.client.queue()
         {
             Ok( x ) =>
             {
-                let mut tmp : Vec<&(String,String)> = x.flds.iter().filter( |&x| x.0 == "directory" || with_file && x.0 == "file" ).collect();
-
-                if wmatch.is_some()
+                let matching = | pattern : &glob::Pattern, song : &Song |
                 {
-                    let wmatch_ptn = wildmatch::WildMatch::new( &wmatch.unwrap() );
+                    let ( _p_dir, c_name ) = Self::make_parent_path( &song.file );
 
-                    tmp = tmp.iter().map( |x| *x ).filter( |&x| wmatch_ptn.is_match( &x.1 ) ).collect();
-                }
+                    song.id.is_some() && if by_path { pattern.matches_within( &song.file ) } else { pattern.matches_name( &c_name ) }
+                };
 
-                let mut ret = Vec::<String>::new();
+                let pattern = glob::literal_first( &self.args[1], false, |p| x.iter().any( |song| matching( p, song ) ) );
+
+                let targets : Vec< ( usize, Song ) > = x.into_iter().enumerate().filter( |( _, song )| matching( &pattern, song ) ).collect();
+
+                let cmds : Vec< String > = targets.iter().filter_map( |( _, song )|
+                    {
+                        song.id.map( |x| make_command_1( "deleteid", &x.to_string() ) )
+                    }
+                ).collect();
+
+                let ( done, err ) = match self.client.exec_command_batch( &cmds, false )
+                {
+                    Ok(_)               => ( targets.len(), None )
+                ,   Err( ( idx, x ) )   => ( idx, Some( x ) )
+                };
 
-                for ( _, nm ) in tmp
+                for ( pos, song ) in &targets[ .. done ]
                 {
-                    let mut val = nm.clone();
+                    outln!( self, " D {:4}| {:9}: {}", pos, EntryKind::File, song.file );
+                }
 
-                    if val.contains( ' ' )
+                if let Some( x ) = err
+                {
+                    if let Some( ( pos, song ) ) = targets.get( done )
                     {
-                        val = String::from( "\"" ) + &val + "\"";
+                        outln!( self, " ! {:4}| {:9}: {}", pos, EntryKind::File, song.file );
                     }
 
-                    ret.push( val );
+                    self.show_error( &x );
+                }
+                else if done == 0
+                {
+                    outln!( self, "No songs matched..." );
                 }
+            }
+        ,   Err( x ) => self.show_error( &x )
+        }
+    }
+
+    fn cmd_switch( &self, cmd1 : &str )
+    {
+        if self.args.len() > 1
+        {
+            self.cmd_with_args( cmd1, 1 )
+        }
+        else
+        {
+            let key = match cmd1
+            {
+                "setvol" => { "volume" }
+            ,   _        => { cmd1 }
+            };
 
-                return ( ret, 0 );
+            match self.client.status()
+            {
+                Ok( x ) =>
+                {
+                    if let Some( v ) = x.get( key )
+                    {
+                        outln!( self );
+                        outln!( self, "{:>10}: {}", key, v );
+                        outln!( self );
+                    }
+                }
+            ,   Err( x ) => self.show_error( &x )
             }
-        ,   Err( x ) => {
-                if x.err_code == 50 /* No such object */
+        }
+    }
+
+    /// Picks files of the database, songs of the playlist or stored playlists, and prints them for `$(…)`.
+    fn cmd_pick( &mut self )
+    {
+        let items : Result< Vec< pick::Item >, Ack > = if self.has_opt( 'q' )
+        {
+            self.client.queue().map( |x| x.into_iter().enumerate().map( |( pos, song )|
                 {
-                    let ( p_dir, c_name ) = Self::make_parent_path( &dir );
+                    let name = song.display_name();
 
-                    match self.exec_command( &Self::make_command_1( "listfiles", &p_dir ) )
-                    {
-                        Ok( x ) =>
-                            {
-                                let tmp : Vec<&(String,String)> = x.flds.iter().filter( |&x| x.0 == "directory" || with_file && x.0 == "file" ).collect();
+                    let label = if name == song.file { format!( "{:4}| {}", pos, name ) } else { format!( "{:4}| {} ({})", pos, name, song.file ) };
 
-                                let mut ret = Vec::<String>::new();
+                    pick::Item { label, value: pos.to_string() }
+                }
+            ).collect() )
+        }
+        else if self.has_opt( 'p' )
+        {
+            self.client.playlists().map( |x| x.into_iter().map( |x| pick::Item { label: x.name.clone(), value: x.name } ).collect() )
+        }
+        else
+        {
+            self.list_cached( "listall", "" ).map( |x| x.into_iter().filter( |x| x.kind == EntryKind::File ).map( |x|
+                {
+                    pick::Item { value: format!( "/{}", x.name ), label: x.name }
+                }
+            ).collect() )
+        };
 
-                                for ( _, nm ) in tmp
-                                {
-                                    if nm.starts_with( &c_name )
-                                    {
-                                        let mut val = nm.clone();
+        let items = match items
+        {
+            Ok( x )     => x
+        ,   Err( x )    =>
+            {
+                self.show_error( &x );
+                return;
+            }
+        };
 
-                                        if val.contains( ' ' )
-                                        {
-                                            val = String::from( "\"" ) + &val + "\"";
-                                        }
+        let query   = self.args[ 1 .. ].join( " " );
+        let ranked  = pick::rank( &items, &query );
 
-                                        ret.push( val );
-                                    }
-                                }
+        if ranked.is_empty()
+        {
+            self.show_fail( "No match..." );
+            return;
+        }
 
-                                return ( ret, c_name.len() );
-                            }
-                        ,   Err(_) => {}
-                    }
+        let external = match self.picker.as_deref()
+        {
+            Some( "builtin" )   => Ok( None )
+        ,   x                   => pick::external( x, &items, &ranked )
+        };
+
+        let picked = match external
+        {
+            Ok( Some( x ) )                             => Ok( x )
+        ,   Ok( None ) if io::stdin().is_terminal()     => pick::builtin( &items, &query )
+        ,   Ok( None )                                  =>
+            {
+                self.show_fail( "pick needs fzf or a terminal..." );
+                return;
+            }
+        ,   Err( x )                                    => Err( x )
+        };
+
+        match picked
+        {
+            Ok( x ) if x.is_empty() => self.show_fail( "Nothing picked..." )
+        ,   Ok( x )                 =>
+            {
+                for i in x
+                {
+                    outln!( self, "{}", items[ i ].value );
                 }
             }
+        ,   Err( x )                => self.show_fail( &format!( "error.. ({})", x ) )
         }
+    }
 
-        return ( Vec::<String>::new(), 0 )
+    fn print_output( &self, output : &Output )
+    {
+        let plugin = output.plugin.as_ref().map( |x| format!( " ({})", x ) ).unwrap_or_default();
+
+        outln!( self, "{:4}| {:3}| {}{}", output.id, if output.enabled { "on" } else { "off" }, output.name, plugin );
     }
 
-    fn cmd_with_args( &self, cmd1 : &str, num_args : usize )
+    fn cmd_output( &self )
     {
-        let mut cmd = String::new();
+        let outputs = match self.client.outputs()
+        {
+            Ok( x )     => x
+        ,   Err( x )    =>
+            {
+                self.show_error( &x );
+                return;
+            }
+        };
 
-        cmd.push_str( cmd1 );
+        if self.args.len() < 2
+        {
+            outln!( self );
+
+            for x in &outputs
+            {
+                self.print_output( x );
+            }
+
+            outln!( self );
+            return;
+        }
 
-        let mut i = 0;
+        let target = outputs.iter().find( |x| x.name == self.args[1] )
+            .or_else( || outputs.iter().find( |x| x.id.to_string() == self.args[1] ) );
 
-        for x in self.args.iter().skip(1)
+        let target = match target
         {
-            if i >= num_args
+            Some( x )   => x
+        ,   None        =>
             {
-                break;
+                self.show_fail( &format!( "No such output... {}", self.args[1] ) );
+                return;
             }
+        };
+
+        if self.args.len() < 3
+        {
+            outln!( self );
+            self.print_output( target );
+            outln!( self );
+            return;
+        }
 
-            i += 1;
+        let cmd1 = match self.args[2].as_str()
+        {
+            "0"         => "disableoutput"
+        ,   "1"         => "enableoutput"
+        ,   "toggle"    => "toggleoutput"
+        ,   _           =>
+            {
+                self.show_fail( "STATE should be 0, 1 or toggle..." );
+                return;
+            }
+        };
 
-            cmd.push_str( " " );
-            cmd.push_str( &Self::quote_arges( x ) );
+        match self.client.exec_command( &make_command_1( cmd1, &target.id.to_string() ) )
+        {
+            Ok(_)       => outln!( self, "OK." )
+        ,   Err( x )    => self.show_error( &x )
         }
+    }
+
+    fn print_song( &self, label : &str, song : &Song )
+    {
+        outln!( self );
+        outln!( self, "{:>10}: {}", label,      song.file );
+        outln!( self, "{:>10}: {}", "Artist",   song.artist().unwrap_or_default() );
+        outln!( self, "{:>10}: {}", "Title",    song.title().unwrap_or_default() );
+        outln!( self, "{:>10}: {}", "Album",    song.album().unwrap_or_default() );
+    }
 
-        match self.exec_command( &cmd )
+    fn cmd_status( &self )
+    {
+        match self.client.status()
         {
-            Ok(_) =>
+            Ok( st ) =>
             {
-                println!( "OK." );
+                outln!( self );
+                outln!( self, "{:>10}: {}", "State",    st.state );
+                outln!( self );
+                outln!( self, "{:>10}: {}", "Volume",   st.get( "volume"    ).unwrap_or_default() );
+                outln!( self, "{:>10}: {}", "Repeat",   st.get( "repeat"    ).unwrap_or_default() );
+                outln!( self, "{:>10}: {}", "Random",   st.get( "random"    ).unwrap_or_default() );
+                outln!( self, "{:>10}: {}", "Single",   st.single );
+
+                if st.song_id.is_some()
+                {
+                    match self.client.queue()
+                    {
+                        Ok( x ) =>
+                        {
+                            if let Some( song ) = x.iter().find( |x| x.id == st.song_id )
+                            {
+                                self.print_song( "Now song", song );
+
+                                if let Ok(x) = Self::format_duration( st.get( "duration" ).unwrap_or_default() )
+                                {
+                                    outln!( self, "{:>10}: {}", "Duration", &x );
+                                };
+
+                                if let Ok(x) = Self::format_duration( st.get( "elapsed" ).unwrap_or_default() )
+                                {
+                                    outln!( self, "{:>10}: {}", "Elapsed",  &x );
+                                }
+
+                                if let Some( audio ) = &st.audio
+                                {
+                                    let mut tmp = audio.clone();
+
+                                    if let Some( bitrate ) = st.bitrate
+                                    {
+                                        tmp = format!( "{} (bitrate: {} Kbps)", tmp, bitrate );
+                                    }
+
+                                    outln!( self, "{:>10}: {}",     "Audio", tmp );
+                                }
+
+                                if st.next_song_id.is_some()
+                                {
+                                    if let Some( song ) = x.iter().find( |x| x.id == st.next_song_id )
+                                    {
+                                        self.print_song( "Next song", song );
+                                    }
+                                }
+                            }
+                        }
+                    ,   Err( x ) => self.show_error( &x )
+                    }
+                }
+
+                outln!( self );
             }
-        ,   Err(x) => self.show_error( &x )
+        ,   Err( x ) => self.show_error( &x )
         }
     }
 
-    fn cmd_switch( &self, cmd1 : &str )
+    fn cmd_quit( &mut self )
     {
-        if self.args.len() > 1
+        self.quit = true;
+
+        if let Err( x ) = self.client.close()
         {
-            self.cmd_with_args( cmd1, 1 )
+            self.show_error( &x );
+        }
+    }
+
+    fn cmd_cmd( &self )
+    {
+        if self.args.len() < 2
+        {
+            self.show_fail( "Please specify MPD Command..." )
         }
         else
         {
-            let key = match cmd1
+            let mut cmd = String::new();
+
+            cmd.push_str( &self.args[1] );
+
+            for x in self.args.iter().skip(2)
             {
-                "setvol" => { "volume" }
-            ,   _        => { cmd1 }
-            };
+                cmd.push(' ');
+                cmd.push_str( &quote_arg( x ) );
+            }
 
-            match self.exec_command( "status" )
+            match self.client.exec_command( &cmd )
             {
                 Ok( x ) =>
                 {
                     for ( k, v ) in x.flds
                     {
-                        if k == key
-                        {
-                            println!( "" );
-                            println!( "{:>10}: {}", k, v );
-                            println!( "" );
-                            break;
-                        }
+                        outln!( self, "{}: {}", k, v );
                     }
                 }
             ,   Err( x ) => self.show_error( &x )
@@ -982,288 +2070,339 @@ impl Mpdsh
         }
     }
 
-    fn cmd_status( &self )
+    fn image_ext( data: &[u8], mime: &Option<String> ) -> &'static str
+    {
+        match mime.as_deref()
+        {
+            Some( "image/jpeg" )    => return "jpg"
+        ,   Some( "image/png" )     => return "png"
+        ,   Some( "image/gif" )     => return "gif"
+        ,   Some( "image/webp" )    => return "webp"
+        ,   _                       => {}
+        }
+
+        if data.starts_with( &[ 0xff, 0xd8, 0xff ] )
+        {
+            "jpg"
+        }
+        else if data.starts_with( b"\x89PNG" )
+        {
+            "png"
+        }
+        else if data.starts_with( b"GIF8" )
+        {
+            "gif"
+        }
+        else
+        {
+            "bin"
+        }
+    }
+
+    fn cmd_cover( &self )
+    {
+        if self.args.len() < 2
+        {
+            self.show_fail( "Please specify FILE..." );
+            return;
+        }
+
+        let mut uri = self.get_arge1_path();
+
+        // a song of the virtual tree has its real path
+        if let Some( x ) = vdir::VirtualDir::parse( &uri ).filter( vdir::VirtualDir::is_song )
+        {
+            match x.songs( &self.client )
+            {
+                Ok( x )     => { uri = x[0].name.clone(); }
+            ,   Err( x )    => { self.show_error( &x ); return; }
+            }
+        }
+
+        let mut ret = self.client.exec_binary( "albumart", &uri );
+
+        if let Ok( None ) | Err( Ack { code: AckCode::NoExist, .. } ) = ret
+        {
+            ret = self.client.exec_binary( "readpicture", &uri );
+        }
+
+        match ret
+        {
+            Ok( Some( ( data, mime ) ) ) =>
+            {
+                let out = match self.opt_value( 'o' )
+                {
+                    Some( x )   => String::from( x )
+                ,   None        => format!( "cover.{}", Self::image_ext( &data, &mime ) )
+                };
+
+                match std::fs::write( &out, &data )
+                {
+                    Ok(_)   => outln!( self, "saved: {} ({} bytes)", out, data.len() )
+                ,   Err(x)  => self.show_fail( &format!( "error.. ({}: {})", out, x ) )
+                }
+            }
+        ,   Ok( None )  => outln!( self, "No picture..." )
+        ,   Err( x )    => self.show_error( &x )
+        }
+    }
+
+    fn cmd_password( &mut self )
+    {
+        if self.args.len() < 2
+        {
+            self.show_fail( "Please specify password..." )
+        }
+        else
+        {
+            let password = self.args[1].clone();
+            self.send_password( &password, true );
+        }
+    }
+
+    fn command_names( &self ) -> Vec<String>
+    {
+        self.client.commands().unwrap_or_default()
+    }
+
+    /// Sends `password`. With `verbose`, tells the commands it unlocked. Errors are shown either way.
+    fn send_password( &mut self, password : &str, verbose : bool ) -> bool
     {
-        match self.exec_command( "status" )
+        let before = self.command_names();
+
+        match self.client.password( password )
         {
-            Ok( x ) =>
+            Ok(_) =>
             {
-                let mut st = HashMap::<String, String>::new();
+                let unlocked : Vec<String> = self.command_names().into_iter().filter( |x| !before.contains( x ) ).collect();
 
-                for ( k, v ) in x.flds
+                if verbose && unlocked.is_empty()
+                {
+                    outln!( self, "Password accepted. (no additional commands unlocked)" );
+                }
+                else if verbose
                 {
-                    st.insert( k, v );
+                    outln!( self, "Password accepted. unlocked: {}", unlocked.join( " " ) );
                 }
 
-                let sp = String::new();
+                true
+            }
+        ,   Err( x ) =>
+            {
+                self.show_error( &x );
+                false
+            }
+        }
+    }
 
-                println!( "" );
-                println!( "{:>10}: {}", "State",    &st.get( "state"    ).unwrap_or( &sp ) );
-                println!( "" );
-                println!( "{:>10}: {}", "Volume",   &st.get( "volume"   ).unwrap_or( &sp ) );
-                println!( "{:>10}: {}", "Repeat",   &st.get( "repeat"   ).unwrap_or( &sp ) );
-                println!( "{:>10}: {}", "Repeat",   &st.get( "random"   ).unwrap_or( &sp ) );
-                println!( "{:>10}: {}", "Single",   &st.get( "single"   ).unwrap_or( &sp ) );
+    fn cmdlist() -> Vec<String>
+    {
+        commands::names().map( String::from ).collect()
+    }
 
-                if st.contains_key( "songid" )
-                {
-                    let songid = st.get( "songid" ).unwrap();
+    /// The command names, their aliases and the user aliases. For completion.
+    fn cmdlist_with_aliases( &self ) -> Vec<String>
+    {
+        let mut ret : Vec<String> = commands::names_with_aliases().map( String::from ).collect();
 
-                    match self.exec_command( "playlistinfo" )
-                    {
-                        Ok( x ) =>
-                        {
-                            let mut pls = HashMap::<String, HashMap::<String, String> >::new();
-                            let mut ple = HashMap::<String, String>::new();
+        ret.extend( self.aliases.keys().filter( |x| !ret.contains( x ) ).cloned().collect::< Vec<String> >() );
 
-                            for ( k, v ) in x.flds.iter().rev()
-                            {
-                                ple.insert( String::from( k ), String::from( v ) );
+        ret
+    }
 
-                                if k == "file"
-                                {
-                                    if ple.contains_key( "Id" )
-                                    {
-                                        pls.insert( String::from( ple.get( "Id" ).unwrap() ), ple );
-                                    }
+    /// Runs an alias. `$1`..`$9` and `$@` are replaced by the arguments, which are appended when neither is used.
+    fn run_alias( &mut self, body : &str ) -> bool
+    {
+        if self.alias_depth >= ALIAS_DEPTH_MAX
+        {
+            self.show_fail( &format!( "alias nested too deep.. ({})", self.line_args[0] ) );
+            return false;
+        }
 
-                                    ple = HashMap::< String, String >::new();
-                                }
-                            }
+        let body = parse::expand_alias( body, &self.line_args[ 1 .. ] );
 
-                            if pls.contains_key( songid )
-                            {
-                                let ple = pls.get( songid ).unwrap();
+        self.alias_depth += 1;
 
-                                println!( "" );
-                                println!( "{:>10}: {}", "Now song", &ple.get( "file"    ).unwrap_or( &sp ) );
-                                println!( "{:>10}: {}", "Artist",   &ple.get( "Artist"   ).unwrap_or( &sp ) );
-                                println!( "{:>10}: {}", "Title",    &ple.get( "Title"    ).unwrap_or( &sp ) );
-                                println!( "{:>10}: {}", "Album",    &ple.get( "Album"    ).unwrap_or( &sp ) );
+        let quit = self.run_line( &body );
 
-                                if let Ok(x) = Self::format_duration( &st.get( "duration" ).unwrap_or( &sp ) )
-                                {
-                                    println!( "{:>10}: {}", "Duration", &x );
-                                };
+        self.alias_depth -= 1;
 
-                                if let Ok(x) = Self::format_duration( &st.get( "elapsed" ).unwrap_or( &sp ) )
-                                {
-                                    println!( "{:>10}: {}", "Elapsed",  &x );
-                                }
+        quit
+    }
 
-                                if st.contains_key( "audio" )
-                                {
-                                    let mut tmp = String::from( st.get( "audio" ).unwrap_or( &sp ) );
+    fn print_alias( &self, name : &str, body : &str )
+    {
+        outln!( self, "alias {} = {}", name, shell_words::quote( body ) );
+    }
 
-                                    if st.contains_key( "bitrate" )
-                                    {
-                                        tmp = String::from( format!( "{} (bitrate: {} Kbps)", tmp, st.get( "bitrate"    ).unwrap_or( &sp ) ) );
-                                    }
+    fn cmd_alias( &mut self )
+    {
+        if self.line_args.len() < 2
+        {
+            for ( name, body ) in &self.aliases
+            {
+                self.print_alias( name, body );
+            }
 
-                                    println!( "{:>10}: {}",     "Audio", tmp );
-                                }
+            return;
+        }
 
-                                if st.contains_key( "nextsongid" )
-                                {
-                                    let songid = st.get( "nextsongid" ).unwrap();
+        // alias NAME = BODY ..., alias NAME BODY ..., alias NAME=BODY
+        let mut rest : Vec<String> = self.line_args[ 1 .. ].to_vec();
 
-                                    if pls.contains_key( songid )
-                                    {
-                                        let ple = pls.get( songid ).unwrap();
+        if let Some( x ) = rest[0].find( '=' ).filter( |&x| x > 0 )
+        {
+            let first = rest.remove( 0 );
+            rest.insert( 0, String::from( &first[ x + 1 .. ] ) );
+            rest.insert( 0, String::from( "=" ) );
+            rest.insert( 0, String::from( &first[ .. x ] ) );
+        }
 
-                                        println!( "" );
-                                        println!( "{:>10}: {}", "Next song",    &ple.get( "file"    ).unwrap_or( &sp ) );
-                                        println!( "{:>10}: {}",     "Artist",       &ple.get( "Artist"  ).unwrap_or( &sp ) );
-                                        println!( "{:>10}: {}",     "Title",        &ple.get( "Title"   ).unwrap_or( &sp ) );
-                                        println!( "{:>10}: {}",     "Album",        &ple.get( "Album"   ).unwrap_or( &sp ) );
-                                    }
-                                }
-                            }
-                        }
-                    ,   Err( x ) => self.show_error( &x )
-                    }
-                }
+        let name = rest.remove( 0 );
+
+        if !rest.is_empty() && rest[0] == "="
+        {
+            rest.remove( 0 );
+        }
+
+        let body = parse::alias_body( &rest );
 
-                println!( "" );
+        if body.trim().is_empty()
+        {
+            match self.aliases.get( &name )
+            {
+                Some( x )   => self.print_alias( &name, x )
+            ,   None        => self.show_fail( &format!( "No such alias... {}", name ) )
             }
-        ,   Err( x ) => self.show_error( &x )
+
+            return;
         }
-    }
 
-    fn cmd_quit( &self )
-    {
-        match self.exec_command( "quit" )
+        if commands::find( &name ).is_some()
         {
-            Ok(_) => {}
-        ,   Err(x) => self.show_error( &x )
+            self.show_fail( &format!( "Cannot alias a built-in command... {}", name ) );
+            return;
+        }
+
+        if self.save_config
+        {
+            if let Err( x ) = config::Config::save_alias( &name, Some( &body ) )
+            {
+                self.show_fail( &format!( "Config Error... {}", x ) );
+            }
         }
+
+        self.aliases.insert( name, body );
     }
 
-    fn cmd_cmd( &self )
+    fn cmd_unalias( &mut self )
     {
-        if self.args.len() < 2
+        if self.line_args.len() < 2
         {
-            println!( "Please specify MPD Command..." )
+            self.show_fail( "Please specify alias name..." );
+            return;
         }
-        else
-        {
-            let mut cmd = String::new();
 
-            cmd.push_str( &self.args[1] );
+        let names : Vec<String> = self.line_args.iter().skip( 1 ).cloned().collect();
 
-            for x in self.args.iter().skip(2)
+        for name in names
+        {
+            if self.aliases.remove( &name ).is_none()
             {
-                cmd.push_str( " " );
-                cmd.push_str( &Self::quote_arges( x ) );
+                self.show_fail( &format!( "No such alias... {}", name ) );
+                continue;
             }
 
-            match self.exec_command( &cmd )
+            if self.save_config
             {
-                Ok( x ) =>
+                if let Err( x ) = config::Config::save_alias( &name, None )
                 {
-                    for ( k, v ) in x.flds
-                    {
-                        println!( "{}: {}", k, v );
-                    }
+                    self.show_fail( &format!( "Config Error... {}", x ) );
                 }
-            ,   Err( x ) => self.show_error( &x )
             }
         }
     }
 
-    fn cmdlist() -> Vec<String>
-    {
-        vec![
-            "cd"
-        ,   "ls"
-
-        ,   "pl"
-        ,   "add"
-        ,   "add_top"
-        ,   "add_uri"
-        ,   "del"
-        ,   "clr"
-        ,   "move"
-
-        ,   "play"
-        ,   "stop"
-        ,   "pause"
-        ,   "resume"
-        ,   "prev"
-        ,   "next"
-
-        ,   "random"
-        ,   "repeat"
-        ,   "single"
-        ,   "volume"
-
-        ,   "status"
-
-        ,   "update"
-        ,   "cmd"
-
-        ,   "quit"
-        ,   "help"
-        ].iter().map( |&x| String::from( x ) ).collect()
-    }
-
     fn cmd_help( &self )
     {
         if self.args.len() >= 2
         {
-            let msg = match self.args[1].as_str()
+            if let Some( x ) = self.aliases.get( &self.args[1] )
             {
-                "cd"                    => HELP_CD
-            ,   "ls"                    => HELP_LS
-
-            ,   "pl"        | "plist"   => HELP_PL
-            ,   "add"       | "a"       => HELP_ADD
-            ,   "add_top"   | "at"      => HELP_ADD_TOP
-            ,   "add_uri"               => HELP_ADD_URI
-            ,   "del"                   => HELP_DEL
-            ,   "clr"                   => HELP_CLR
-            ,   "move"                  => HELP_MOVE
+                outln!( self );
+                self.print_alias( &self.args[1], x );
+            }
 
-            ,   "play"      | "p"       => HELP_PLAY
-            ,   "stop"      | "s"       => HELP_STOP
-            ,   "pause"     | "u"       => HELP_PAUSE
-            ,   "resume"    | "e"       => HELP_RESUME
+            if let Some( x ) = commands::find( &self.args[1] )
+            {
+                out!( self, "{}", x.help.trim_end() );
+                outln!( self );
 
-            ,   "prev"      | "r"       => HELP_PREV
-            ,   "next"      | "n"       => HELP_NEXT
+                if !x.aliases.is_empty()
+                {
+                    outln!( self, " - alias( {} )", x.aliases.join( ", " ) );
+                }
+            }
+        }
 
-            ,   "random"                => HELP_RANDOM
-            ,   "repeat"                => HELP_REPEAT
-            ,   "single"                => HELP_SINGLE
-            ,   "volume"    | "v"       => HELP_VOLUME
+        outln!( self );
+        out!( self, "help [ " );
 
-            ,   "status"                    => HELP_STATUS
+        for x in Self::cmdlist()
+        {
+            out!( self, "{} ", &x );
+        }
 
-            ,   "update"                => HELP_UPDATE
-            ,   "cmd"                   => HELP_CMD
+        outln!( self, "]" );
 
-            ,   "help"      | "h"       => HELP_HELP
-            ,   "quit"      | "q"       => HELP_QUIT
-            ,   _                       => { "" }
-            };
+        if !self.aliases.is_empty()
+        {
+            out!( self, "alias [ " );
 
-            if !msg.is_empty()
+            for x in self.aliases.keys()
             {
-                println!( "{}", &msg );
+                out!( self, "{} ", &x );
             }
-        }
 
-        println!( "" );
-        print!( "help [ " );
-
-        for x in Self::cmdlist()
-        {
-            print!( "{} ", &x );
+            outln!( self, "]" );
         }
 
-        println!( "]" );
-        println!( "" );
+        outln!( self );
     }
 
     fn cmd_unknown( &self )
     {
-        println!( "unknown.. (use help command)" )
+        self.show_fail( "unknown.. (use help command)" )
     }
 
-    fn show_error( &self, err : &ExecErr )
-    {
-        println!( "error.. ({})", err )
-    }
+    // the messages below go to stderr, never into the output of a command ( `> file`, `| grep`, `$(…)` )
 
-    fn quote_arges( arg: &str ) -> String
+    fn show_usage( &self, cmd : &commands::Command, msg : &str )
     {
-        let mut arg = String::from( arg.replace('\\', r"\\").replace('"', r#"\""#) );
-
-        if arg.contains( ' ' )
-        {
-            arg = String::from( "\"" ) + &arg + "\""
-        }
+        self.show_fail( &format!( "{}: {}", cmd.name, msg ) );
 
-        arg
+        eprintln!( "usage: {}", cmd.usage() );
     }
 
-    fn make_command_1( cmd: &str, arg1: &str ) -> String
+    fn show_fail( &self, msg : &str )
     {
-        let mut ret = String::from( cmd );
-        ret.push_str( " " );
-        ret.push_str( &Self::quote_arges( arg1 ) );
-        ret
+        self.failed.set( true );
+
+        eprintln!( "{}", msg );
     }
 
-    fn make_command_2( cmd: &str, arg1: &str, arg2: &str ) -> String
+    fn show_error( &self, err : &Ack )
     {
-        let mut ret = String::from( cmd );
-        ret.push_str( " " );
-        ret.push_str( &Self::quote_arges( arg1 ) );
-        ret.push_str( " " );
-        ret.push_str( &Self::quote_arges( arg2 ) );
-        ret
+        self.failed.set( true );
+
+        eprintln!( "error.. {}", err );
+
+        if let Some( x ) = err.code.hint()
+        {
+            eprintln!( "  hint: {}", x );
+        }
     }
+
 }
 
 #[derive( rustyline_derive::Helper, rustyline_derive::Validator, rustyline_derive::Highlighter, rustyline_derive::Hinter)]
@@ -1274,12 +2413,12 @@ struct RlHelper
 
 impl RlHelper
 {
-    fn borrow( &self ) -> Ref< Mpdsh >
+    fn borrow( &self ) -> Ref<'_, Mpdsh >
     {
         self.rc_mpdsh.borrow()
     }
 
-    fn borrow_mut( &self ) -> RefMut< Mpdsh >
+    fn borrow_mut( &self ) -> RefMut<'_, Mpdsh >
     {
         self.rc_mpdsh.borrow_mut()
     }
@@ -1296,13 +2435,18 @@ impl Completer for RlHelper
         _ctx: &Context<'_>,
     ) -> Result<( usize, Vec< String > ), ReadlineError >
     {
-        match shell_words::split( &line )
+        // the text after the cursor is not completed
+        let line = &line[ .. pos ];
+
+        match shell_words::split( parse::last_command( line ) )
         {
             Ok(args) =>
             {
-                let ( entry, posd ) = self.borrow_mut().cmdline_hint( args );
+                let new_arg = line.ends_with( char::is_whitespace );
+                let ( entry, posd ) = self.borrow_mut().cmdline_hint( args, new_arg );
 
-                return Ok( ( pos - posd, entry ) );
+                // `posd` counts the word as the command sees it, the line may quote it
+                return Ok( ( parse::word_start( line, posd ), entry ) );
             }
         ,   Err(_) => { /* nop */ }
         }
@@ -1316,22 +2460,57 @@ fn usage( prog: &str, opts: getopts::Options )
     println!( "{}", opts.usage( &format!("Usage: {} [options]", prog ) ) );
 }
 
-const EX_USAGE: i32 = 64;
+const EX_FAILED:      i32 = 1;
+const EX_USAGE:       i32 = 64;
+const EX_NOINPUT:     i32 = 66;
+const EX_UNAVAILABLE: i32 = 69;
+const EX_IOERR:       i32 = 74;
+const EX_CONFIG:      i32 = 78;
 
-const PKG_NAME:     &'static str = env!("CARGO_PKG_NAME");
-const PKG_VERSION:  &'static str = env!("CARGO_PKG_VERSION");
-const PKG_AUTHORS:  &'static str = env!("CARGO_PKG_AUTHORS");
+const PKG_NAME:     &str = env!("CARGO_PKG_NAME");
+const PKG_VERSION:  &str = env!("CARGO_PKG_VERSION");
 
-fn parse_opt() -> ( String, String, bool )
+/// Splits `password@host` as MPD_HOST does for mpc. A leading `@` is an abstract socket, not a password.
+fn split_host_password( host : &str ) -> ( String, Option<String> )
 {
-    let args: Vec<String> = env::args().collect();
+    match host.find( '@' )
+    {
+        Some( x ) if x > 0  => ( String::from( &host[ x + 1 .. ] ), Some( String::from( &host[ .. x ] ) ) )
+    ,   _                   => ( String::from( host ), None )
+    }
+}
+
+/// Command line options.
+struct Opt
+{
+    host:       String
+,   port:       String
+,   password:   Option<String>
+,   protolog:   bool
+,   command:    Option<String>
+,   file:       Option<String>
+,   keep_going: bool
+,   dir:        Option<String>
+,   aliases:    BTreeMap<String, String>
+,   bookmarks:  BTreeMap<String, String>
+,   picker:     Option<String>
+,   prompt_depth: Option<usize>
+,   cache:      cache::Mode
+}
 
-    let arg_prog = args[0].clone();
+fn parse_opt() -> Opt
+{
+    let args: Vec<String> = env::args().collect();
 
     let mut opts = getopts::Options::new();
 
-    opts.optopt( "h", "host", "MPD host address", "localhost" );
-    opts.optopt( "p", "port", "MPD port number ", "6600" );
+    opts.optopt( "h", "host", "MPD host address, or Unix socket path ( /path or @abstract ). [password@]host is accepted. (env: MPD_HOST)", "localhost" );
+    opts.optopt( "p", "port", "MPD port number (env: MPD_PORT)", "6600" );
+    opts.optopt( "P", "password", "MPD password", "PASSWORD" );
+    opts.optopt( "", "profile", "Use the server profile in ~/.config/mpdsh/config.toml", "NAME" );
+    opts.optopt( "c", "command", "Run the commands ( separated by `;` or newline ) and exit.", "COMMANDS" );
+    opts.optopt( "f", "file", "Run the commands in the script file ( `-` is stdin ) and exit.", "SCRIPT" );
+    opts.optflag( "k", "keep-going", "With -c, -f or piped stdin, do not stop at the first error." );
     opts.optflag( "d", "protolog", "Output protocol log to stderr." );
     opts.optflag( "v", "version", "Print version info and exit." );
     opts.optflag( "", "help", "Print this help menu." );
@@ -1341,7 +2520,7 @@ fn parse_opt() -> ( String, String, bool )
         Ok(m) => { m }
         Err(f) =>
         {
-            println!( "{}", f.to_string() );
+            println!( "{}", f );
             usage( PKG_NAME, opts );
             std::process::exit( EX_USAGE );
         }
@@ -1359,56 +2538,255 @@ fn parse_opt() -> ( String, String, bool )
         std::process::exit( EX_USAGE );
     }
 
-    let opt_host = match opt_matches.opt_str( "host" )
+    let conf = match config::Config::load()
+    {
+        Ok(x)   => { x }
+    ,   Err(x)  =>
+        {
+            eprintln!( "Config Error... {}", x );
+            std::process::exit( EX_CONFIG );
+        }
+    };
+
+    let profile = match opt_matches.opt_str( "profile" )
+    {
+        Some( name ) =>
+        {
+            match conf.profile( &name )
+            {
+                Some(x) => { x.clone() }
+            ,   None    =>
+                {
+                    eprintln!( "No such profile... {}", name );
+                    std::process::exit( EX_USAGE );
+                }
+            }
+        }
+    ,   None => { config::Profile::default() }
+    };
+
+    let opt_host = match opt_matches.opt_str( "host" ).or_else( || profile.socket.clone() ).or_else( || profile.host.clone() ).or_else( || env::var( "MPD_HOST" ).ok() )
     {
         Some(x) => { x }
     ,   None    => { "localhost".to_string() }
     };
 
-    let opt_port = match opt_matches.opt_str( "port" )
+    let opt_port = match opt_matches.opt_str( "port" ).or_else( || profile.port.map( |x| x.to_string() ) ).or_else( || env::var( "MPD_PORT" ).ok() )
     {
         Some(x) => { x }
     ,   None    => { "6600".to_string() }
     };
 
-    let opt_protolog = opt_matches.opt_present( "protolog" );
+    let ( opt_host, host_password ) = split_host_password( &opt_host );
+
+    Opt
+    {
+        host:       opt_host
+    ,   port:       opt_port
+    ,   password:   opt_matches.opt_str( "password" ).or( profile.password ).or( host_password )
+    ,   protolog:   opt_matches.opt_present( "protolog" )
+    ,   command:    opt_matches.opt_str( "command" )
+    ,   file:       opt_matches.opt_str( "file" )
+    ,   keep_going: opt_matches.opt_present( "keep-going" )
+    ,   dir:        profile.dir
+    ,   aliases:    conf.aliases
+    ,   bookmarks:  conf.bookmarks
+    ,   picker:     conf.picker
+    ,   prompt_depth: conf.prompt_depth
+    ,   cache:      conf.cache.unwrap_or_default()
+    }
+}
+
+/// Runs the commands of `input` without a prompt. Returns the exit status, and true if `quit` was run.
+fn run_script( mpdsh : &mut Mpdsh, name : &str, input : Box< dyn BufRead >, keep_going : bool ) -> ( i32, bool )
+{
+    let mut status = 0;
+
+    for ( n, line ) in input.lines().enumerate()
+    {
+        let line = match line
+        {
+            Ok(x)   => { x }
+        ,   Err(x)  =>
+            {
+                eprintln!( "Read Error... {} ({})", name, x );
+                return ( EX_IOERR, false );
+            }
+        };
+
+        let quit = mpdsh.run_line( &line );
+
+        if mpdsh.failed()
+        {
+            eprintln!( "{}: line {}: failed: {}", name, n + 1, line.trim() );
+
+            status = EX_FAILED;
+
+            if !keep_going
+            {
+                return ( status, false );
+            }
+        }
+
+        if quit
+        {
+            return ( status, true );
+        }
+    }
+
+    ( status, false )
+}
+
+/// Sends the password, changes to the profile directory and runs `~/.mpdshrc`.
+/// Returns false if the password or the directory failed. A script ( not `interactive` ) is told nothing else.
+fn startup( mpdsh : &mut Mpdsh, opt : &Opt, interactive : bool ) -> bool
+{
+    let mut ok = true;
+
+    mpdsh.aliases       = opt.aliases.clone();
+
+    // an alias would hide the command, as `alias` refuses
+    for name in opt.aliases.keys().filter( |x| commands::find( x ).is_some() )
+    {
+        mpdsh.aliases.remove( name );
+        mpdsh.show_fail( &format!( "Cannot alias a built-in command... {} ( config.toml )", name ) );
+    }
+
+    mpdsh.bookmarks     = opt.bookmarks.clone();
+    mpdsh.picker        = opt.picker.clone();
+    mpdsh.prompt_depth  = opt.prompt_depth;
+    mpdsh.cache         = cache::DirCache::new( opt.cache, &ConnAddr::new( &opt.host, &opt.port ).to_string() );
+
+    if let Some( x ) = &opt.password
+    {
+        // a script prints only what its commands print
+        ok &= mpdsh.send_password( x, interactive );
+    }
+
+    mpdsh.load_cache();
+
+    if let Some( x ) = &opt.dir
+    {
+        mpdsh.cmdline( vec![ String::from( "cd" ), x.clone() ] );
+        ok &= !mpdsh.failed();
+    }
+
+    if let Some( path ) = config::rc_path()
+    {
+        if let Ok( f ) = std::fs::File::open( &path )
+        {
+            mpdsh.save_config = false;
+            run_script( mpdsh, &path.display().to_string(), Box::new( io::BufReader::new( f ) ), true );
+            mpdsh.save_config = true;
+        }
+    }
 
-    return( opt_host, opt_port, opt_protolog );
+    ok
 }
 
+const ALIAS_DEPTH_MAX : usize = 16;
+
 const HISTORY_FILENAME : &str = ".mdpsh_history";
 
 fn main()
 {
-    let ( opt_host, opt_port, opt_protolog ) = parse_opt();
+    let opt = parse_opt();
+
+    let script : Option< ( String, Box< dyn BufRead > ) > = match ( &opt.command, &opt.file )
+    {
+        ( Some( x ), _ ) => Some( ( String::from( "-c" ), Box::new( io::Cursor::new( x.clone().into_bytes() ) ) ) )
+    ,   ( None, Some( x ) ) if x == "-" => Some( ( String::from( "<stdin>" ), Box::new( io::stdin().lock() ) ) )
+    ,   ( None, Some( x ) ) =>
+        {
+            match std::fs::File::open( x )
+            {
+                Ok( f ) => Some( ( x.clone(), Box::new( io::BufReader::new( f ) ) ) )
+            ,   Err( err ) =>
+                {
+                    eprintln!( "Open Error... {} ({})", x, err );
+                    std::process::exit( EX_NOINPUT );
+                }
+            }
+        }
+    ,   ( None, None ) if !io::stdin().is_terminal() => Some( ( String::from( "<stdin>" ), Box::new( io::stdin().lock() ) ) )
+    ,   ( None, None ) => None
+    };
+
+    if let Some( ( name, input ) ) = script
+    {
+        let addr = ConnAddr::new( &opt.host, &opt.port );
+
+        let conn_notify : ConnNotify = Box::new( |msg| eprintln!( "{}", msg ) );
+
+        let mut mpdsh = match addr.connect( opt.protolog, None, Some( conn_notify ) )
+        {
+            Ok(x) => { Mpdsh::new( x ) }
+        ,   Err(x) => {
+                eprintln!( "Connecting Error... {} ({})", addr, x );
+                std::process::exit( EX_UNAVAILABLE );
+            }
+        };
 
-    let sockaddr_str = format!( "{}:{}", &opt_host, &opt_port );
+        if !startup( &mut mpdsh, &opt, false ) && !opt.keep_going
+        {
+            std::process::exit( EX_FAILED );
+        }
+
+        mpdsh.stop_on_error = !opt.keep_going;
+
+        let ( status, quit ) = run_script( &mut mpdsh, &name, input, opt.keep_going );
+
+        if !quit
+        {
+            mpdsh.cmdline( vec![ String::from( "quit" ) ] );
+        }
+
+        mpdsh.save_cache();
 
-    println!( "Connecting... {}", sockaddr_str );
+        std::process::exit( status );
+    }
 
-    let stream = match net::TcpStream::connect( &sockaddr_str )
+    let mut rl = match rustyline::Editor::< RlHelper >::new()
     {
         Ok(x) => { x }
-    ,   Err(_) => {
-            println!( "Connecting Error... {}", &sockaddr_str );
+    ,   Err(x) => {
+            println!( "Error: {:?}", x );
             return;
         }
     };
 
-    let mpdsh = match Mpdsh::new( stream, opt_protolog )
+    let notify : Option< IdleNotify > = match rl.create_external_printer()
     {
-        Ok(x) => { x }
-    ,   Err(_) => {
-            println!( "Connecting Error... {}", &sockaddr_str );
-            return;
-        }
+        Ok( mut printer )   => Some( Box::new( move |msg| { let _ = printer.print( msg ); } ) )
+    ,   Err(_)              => None
     };
 
-    let mut rl = rustyline::Editor::< RlHelper >::new();
+    // the reconnects are printed above the prompt as well, or to stderr
+    let conn_notify : ConnNotify = match rl.create_external_printer()
+    {
+        Ok( mut printer )   => Box::new( move |msg| { let _ = printer.print( msg ); } )
+    ,   Err(_)              => Box::new( |msg| eprintln!( "{}", msg ) )
+    };
+
+    let addr = ConnAddr::new( &opt.host, &opt.port );
+
+    println!( "Connecting... {}", addr );
 
-    if rl.load_history( HISTORY_FILENAME ).is_err()
+    let mut mpdsh = match addr.connect( opt.protolog, notify, Some( conn_notify ) )
     {
-    }
+        Ok(x) => {
+            println!( "connected OK MPD {}", x.version() );
+            Mpdsh::new( x )
+        }
+    ,   Err(x) => {
+            println!( "Connecting Error... {} ({})", addr, x );
+            std::process::exit( EX_UNAVAILABLE );
+        }
+    };
+
+    startup( &mut mpdsh, &opt, true );
+
+    let _ = rl.load_history( HISTORY_FILENAME );
 
     rl.set_helper( Some( RlHelper{ rc_mpdsh : RefCell::new( mpdsh ) } ) );
 
@@ -1423,19 +2801,9 @@ fn main()
             {
                 rl.add_history_entry( line.as_str() );
 
-                match shell_words::split( &line )
+                if rl.helper().unwrap().borrow_mut().run_line( &line )
                 {
-                    Ok(args) =>
-                    {
-                        if rl.helper().unwrap().borrow_mut().cmdline( args )
-                        {
-                            break;
-                        }
-                    }
-                ,   Err(err) =>
-                    {
-                        println!("Error: {:?}", err);
-                    }
+                    break;
                 }
             }
         ,   Err(ReadlineError::Interrupted) =>
@@ -1456,155 +2824,7 @@ fn main()
         }
     }
 
+    rl.helper().unwrap().borrow().save_cache();
+
     rl.save_history( HISTORY_FILENAME ).unwrap();
 }
-
-const HELP_CD : &str = "
-cd [<DIR>]
- - change directory
- - You can use the <TAB> key for completion.
-";
-
-const HELP_LS : &str = "
-ls [-l] [<DIR|FILE>]
- - list file or directory
- - [-l] more info ( file only )
- - You can use the <TAB> key for completion.
-";
-
-const HELP_PL : &str = "
-pl [-l]
- - show playlist
- - [-l] more info
- - FLG `=>` The current song stopped on or playing.
- - FLG `.`  The next song to be played.
- - alias( plist )
-";
-
-const HELP_ADD : &str = "
-add [<FILE|DIR>]
- - Adds the file to the playlist (directories add recursively).
- - If no file is specified, all files under the current directory are targeted.
- - You can use the <TAB> key for completion.
- - alias( a )
-";
-
-const HELP_ADD_TOP : &str = "
-add_top [<FILE|DIR>]
- - Adds the file to the playlist top (directories add recursively).
- - You can use the <TAB> key for completion.
- - alias( at )
-";
-
-const HELP_ADD_URI : &str = "
-add_uri <URI> [<POSITION>]
- - Adds the file to the playlist.
- - URL of Internet radio, etc.
-";
-
-const HELP_DEL : &str = "
-del <POS>|<START:END>
- - Deletes a song from the playlist.
-";
-
-const HELP_CLR : &str = "
-clr
- - Deletes all songs from the playlist.
-";
-
-const HELP_MOVE : &str = "
-move <POS>|<START:END> <TOPOS>
- - Moves the song in the playlist.
-";
-
-
-const HELP_PLAY : &str = "
-play [<POS>]
- - Begins playing the playlist.
- - alias( p )
-";
-
-const HELP_STOP : &str = "
-stop
- - Stops playing.
- - alias( s )
-";
-
-const HELP_PAUSE : &str = "
-pause
- - Toggles pause playing.
- - alias( u )
-";
-
-const HELP_RESUME : &str = "
-resume
- - Toggles resumes playing.
- - alias( e )
-";
-
-const HELP_PREV : &str = "
-prev
- - Plays previous song in the playlist.
- - alias( r )
-";
-
-const HELP_NEXT : &str = "
-next
- - Plays next song in the playlist.
- - alias( n )
-";
-
-const HELP_RANDOM : &str = "
-random [<STATE>]
- - Sets random state to STATE, STATE should be 0 or 1.
- - Or display the current value.
-";
-
-const HELP_REPEAT : &str = "
-repeat [<STATE>]
- - Sets repeat state to STATE, STATE should be 0 or 1.
- - Or display the current value.
-";
-
-const HELP_SINGLE : &str = "
-single <STATE>
- - Sets single state to STATE, STATE should be 0, 1 or `oneshot`
- - When single is activated, playback is stopped after current song, or song is repeated if the ‘repeat’ mode is enabled.
- - Or display the current value.
-";
-
-const HELP_VOLUME : &str = "
-volume <VOL>
- - Sets volume to VOL, the range of volume is 0-100.
- - Or display the current value.
- - alias( v )
-";
-
-const HELP_STATUS : &str = "
-status
- - Reports the current status of the player and the volume level.
- - alias( st )
-";
-
-const HELP_UPDATE : &str = "
-update
- - Updates the music database on MPD
-";
-
-const HELP_CMD : &str = "
-cmd <MPDCOMMAND> [<MPDCOMMAND_ARG> ...]
- - Exec MPD Protocol command (see:https://www.musicpd.org/doc/html/protocol.html)
-";
-
-const HELP_HELP : &str = "
-help help help ... help!
- - I want you to help me.
- - alias( h )
-";
-
-const HELP_QUIT : &str = "
-quit
- - Quit this program.
- - alias( q )
-";
-
@@ -0,0 +1,128 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		output.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::fs::{ File, OpenOptions };
+use std::io::{ self, Read, Write };
+use std::process::{ Child, ChildStdin, Command, Stdio };
+use std::thread::{ self, JoinHandle };
+
+/// Where the output of a command goes, other than the terminal.
+pub enum Sink
+{
+    /// `$(…)`
+    Capture( Vec<u8> )
+,   /// `> file`, `>> file`
+    File( File )
+,   /// `| command`
+    Pipe
+    {
+        child:  Child
+    ,   stdin:  Option< ChildStdin >
+    ,   reader: Option< JoinHandle< Vec<u8> > >
+    }
+}
+
+impl Sink
+{
+    pub fn file( path: &str, append: bool ) -> io::Result< Sink >
+    {
+        let path = match path.strip_prefix( "~/" )
+        {
+            Some( x )   => dirs::home_dir().map( |h| h.join( x ) ).unwrap_or_else( || path.into() )
+        ,   None        => path.into()
+        };
+
+        let f = OpenOptions::new().write( true ).create( true ).append( append ).truncate( !append ).open( path )?;
+
+        Ok( Sink::File( f ) )
+    }
+
+    /// Starts `cmd` with the local shell. With `capture`, its output is kept for `finish()`,
+    /// otherwise it goes to the terminal.
+    pub fn pipe( cmd: &str, capture: bool ) -> io::Result< Sink >
+    {
+        #[cfg(unix)]
+        let mut command = { let mut x = Command::new( "sh" ); x.arg( "-c" ).arg( cmd ); x };
+        #[cfg(windows)]
+        let mut command = { let mut x = Command::new( "cmd" ); x.arg( "/C" ).arg( cmd ); x };
+
+        command.stdin( Stdio::piped() );
+
+        if capture
+        {
+            command.stdout( Stdio::piped() );
+        }
+
+        let mut child   = command.spawn()?;
+        let stdin       = child.stdin.take();
+
+        // read in another thread, or a full pipe blocks both sides
+        let reader = child.stdout.take().map( |mut x|
+            {
+                thread::spawn( move ||
+                    {
+                        let mut buf = Vec::<u8>::new();
+                        let _ = x.read_to_end( &mut buf );
+                        buf
+                    }
+                )
+            }
+        );
+
+        Ok( Sink::Pipe { child, stdin, reader } )
+    }
+
+    /// Closes the sink. Returns the captured output, and false if the piped command failed.
+    pub fn finish( self ) -> io::Result< ( Vec<u8>, bool ) >
+    {
+        match self
+        {
+            Sink::Capture( x )  => Ok( ( x, true ) )
+        ,   Sink::File( mut x ) => { x.flush()?; Ok( ( Vec::new(), true ) ) }
+        ,   Sink::Pipe { mut child, stdin, reader } =>
+            {
+                drop( stdin );
+
+                let status  = child.wait()?;
+                let buf     = reader.and_then( |x| x.join().ok() ).unwrap_or_default();
+
+                Ok( ( buf, status.success() ) )
+            }
+        }
+    }
+}
+
+impl Write for Sink
+{
+    fn write( &mut self, buf: &[u8] ) -> io::Result< usize >
+    {
+        match self
+        {
+            Sink::Capture( x )          => x.write( buf )
+        ,   Sink::File( x )             => x.write( buf )
+        ,   Sink::Pipe { stdin, .. }    =>
+            {
+                match stdin
+                {
+                    Some( x )   => x.write( buf )
+                ,   None        => Err( io::Error::from( io::ErrorKind::BrokenPipe ) )
+                }
+            }
+        }
+    }
+
+    fn flush( &mut self ) -> io::Result< () >
+    {
+        match self
+        {
+            Sink::Capture(_)            => Ok( () )
+        ,   Sink::File( x )             => x.flush()
+        ,   Sink::Pipe { stdin, .. }    => stdin.as_mut().map_or( Ok( () ), |x| x.flush() )
+        }
+    }
+}
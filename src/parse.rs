@@ -0,0 +1,512 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		parse.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+/// How a command is joined to the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chain
+{
+    /// `;` ( or the first command )
+    Seq
+,   /// `&&` run if the previous command succeeded.
+    And
+,   /// `||` run if the previous command failed.
+    Or
+}
+
+/// Advances over `chars[i]`, tracking quotes and `$(…)` in `stack`. Returns the number of chars consumed.
+fn step( stack: &mut Vec<char>, chars: &[char], i: usize ) -> usize
+{
+    let c       = chars[i];
+    let next    = chars.get( i + 1 ).copied();
+    let escaped = if i + 1 < chars.len() { 2 } else { 1 };
+
+    match stack.last()
+    {
+        Some( '\'' ) =>
+        {
+            if c == '\''
+            {
+                stack.pop();
+            }
+
+            1
+        }
+    ,   Some( '"' ) =>
+        {
+            match c
+            {
+                '\\'                        => escaped
+            ,   '"'                         => { stack.pop(); 1 }
+            ,   '$' if next == Some( '(' )  => { stack.push( '(' ); 2 }
+            ,   _                           => 1
+            }
+        }
+    ,   top =>
+        {
+            match c
+            {
+                '\\'                        => escaped
+            ,   '\'' | '"'                  => { stack.push( c ); 1 }
+            ,   '$' if next == Some( '(' )  => { stack.push( '(' ); 2 }
+            ,   ')' if top == Some( &'(' )  => { stack.pop(); 1 }
+            ,   _                           => 1
+            }
+        }
+    }
+}
+
+/// Splits a line at `;`, `&&` and `||` outside of quotes and `$(…)`.
+/// An unquoted `#` at the start of a word begins a comment.
+pub fn split_chain( line: &str ) -> Vec< ( Chain, String ) >
+{
+    let chars : Vec<char> = line.chars().collect();
+
+    let mut ret     = Vec::< ( Chain, String ) >::new();
+    let mut stack   = Vec::<char>::new();
+    let mut cur     = String::new();
+    let mut chain   = Chain::Seq;
+    let mut i       = 0;
+
+    fn push( ret: &mut Vec< ( Chain, String ) >, cur: &mut String, chain: Chain )
+    {
+        if !cur.trim().is_empty()
+        {
+            ret.push( ( chain, std::mem::take( cur ) ) );
+        }
+
+        cur.clear();
+    }
+
+    while i < chars.len()
+    {
+        if stack.is_empty()
+        {
+            let c       = chars[i];
+            let next    = chars.get( i + 1 ).copied();
+
+            let sep = match ( c, next )
+            {
+                ( ';', _ )          => Some( ( Chain::Seq, 1 ) )
+            ,   ( '&', Some( '&' ) )=> Some( ( Chain::And, 2 ) )
+            ,   ( '|', Some( '|' ) )=> Some( ( Chain::Or,  2 ) )
+            ,   _                   => None
+            };
+
+            if let Some( ( x, n ) ) = sep
+            {
+                push( &mut ret, &mut cur, chain );
+                chain = x;
+                i += n;
+                continue;
+            }
+
+            if c == '#' && cur.chars().last().is_none_or( char::is_whitespace )
+            {
+                break;
+            }
+        }
+
+        let n = step( &mut stack, &chars, i );
+        cur.extend( &chars[ i .. i + n ] );
+        i += n;
+    }
+
+    push( &mut ret, &mut cur, chain );
+
+    ret
+}
+
+/// Replaces each `$(…)` in `cmd` with the output of `run`, one word per line.
+/// Inside double quotes the lines become one word. Returns None if `run` failed.
+pub fn expand_subst< F: FnMut( &str ) -> Option<String> >( cmd: &str, mut run: F ) -> Option<String>
+{
+    let chars : Vec<char> = cmd.chars().collect();
+
+    let mut ret     = String::new();
+    let mut stack   = Vec::<char>::new();
+    let mut i       = 0;
+
+    while i < chars.len()
+    {
+        if chars[i] == '$' && chars.get( i + 1 ) == Some( &'(' ) && stack.last() != Some( &'\'' )
+        {
+            let mut sub = vec![ '(' ];
+            let mut j   = i + 2;
+
+            while j < chars.len() && !sub.is_empty()
+            {
+                j += step( &mut sub, &chars, j );
+            }
+
+            if sub.is_empty()
+            {
+                let inner : String = chars[ i + 2 .. j - 1 ].iter().collect();
+                let output = run( &inner )?;
+                let words : Vec<&str> = output.lines().filter( |x| !x.trim().is_empty() ).collect();
+
+                if stack.last() == Some( &'"' )
+                {
+                    ret.push_str( &words.join( " " ).replace( '\\', "\\\\" ).replace( '"', "\\\"" ) );
+                }
+                else
+                {
+                    ret.push_str( &words.iter().map( |x| shell_words::quote( x ) ).collect::< Vec<_> >().join( " " ) );
+                }
+
+                i = j;
+                continue;
+            }
+        }
+
+        let n = step( &mut stack, &chars, i );
+        ret.extend( &chars[ i .. i + n ] );
+        i += n;
+    }
+
+    Some( ret )
+}
+
+/// The part of `line` after the last `;`, `&&` or `||` outside of quotes and `$(…)`. Used for completion.
+pub fn last_command( line: &str ) -> &str
+{
+    let chars   : Vec<char>     = line.chars().collect();
+    let offsets : Vec<usize>    = line.char_indices().map( |x| x.0 ).collect();
+
+    let mut stack   = Vec::<char>::new();
+    let mut start   = 0;
+    let mut i       = 0;
+
+    while i < chars.len()
+    {
+        if stack.is_empty()
+        {
+            let n = match ( chars[i], chars.get( i + 1 ) )
+            {
+                ( ';', _ )          => 1
+            ,   ( '&', Some( '&' ) )=> 2
+            ,   ( '|', Some( '|' ) )=> 2
+            ,   _                   => 0
+            };
+
+            if n > 0
+            {
+                i += n;
+                start = offsets.get( i ).copied().unwrap_or( line.len() );
+                continue;
+            }
+        }
+
+        i += step( &mut stack, &chars, i );
+    }
+
+    &line[ start .. ]
+}
+
+/// The byte offset in `line` where the last `len` bytes of its last word begin, `len` counted as the command sees
+/// the word ( without quotes and `\\` ). Completion replaces the line from there.
+pub fn word_start( line: &str, len: usize ) -> usize
+{
+    let mut quote   = None;
+    let mut start   = None;
+    let mut word    = Vec::< ( usize, char ) >::new();
+    let mut iter    = line.char_indices().peekable();
+
+    while let Some( ( off, c ) ) = iter.next()
+    {
+        if quote.is_none() && c.is_whitespace()
+        {
+            word.clear();
+            start = None;
+            continue;
+        }
+
+        start.get_or_insert( off );
+
+        match ( quote, c )
+        {
+            ( None, '\\' )                      => if let Some( ( _, x ) ) = iter.next() { word.push( ( off, x ) ) }
+        ,   ( None, '\'' ) | ( None, '"' )      => quote = Some( c )
+        ,   ( Some( '"' ), '\\' ) if iter.peek().is_some_and( |x| "\\\"$`".contains( x.1 ) )
+                                            => word.push( ( off, iter.next().unwrap().1 ) )
+        ,   ( Some( q ), x ) if x == q          => quote = None
+        ,   _                                   => word.push( ( off, c ) )
+        }
+    }
+
+    let mut rest = len;
+    let mut from = word.len();
+
+    while from > 0 && rest >= word[ from - 1 ].1.len_utf8()
+    {
+        rest -= word[ from - 1 ].1.len_utf8();
+        from -= 1;
+    }
+
+    match ( from, start )
+    {
+        _ if len == 0                   => line.len()
+    ,   ( 0, Some( x ) )                => x
+    ,   ( x, _ ) if x < word.len()      => word[ x ].0
+    ,   _                               => line.len()
+    }
+}
+
+lazy_static! {
+    /// `$1`..`$9` and `$@` in the body of an alias.
+    static ref ALIAS_PARAM: regex::Regex = regex::Regex::new( r"\$([1-9@])" ).unwrap();
+}
+
+/// The body of `alias NAME = WORDS...` as a command line again. One word is the commands as they are
+/// ( `alias jz = "cd /Jazz; add"` ). More are quoted one by one, but `$1`..`$9` and `$@` are left for expand_alias.
+pub fn alias_body( words: &[ String ] ) -> String
+{
+    if words.len() == 1
+    {
+        return words[0].clone();
+    }
+
+    // `artist==$1` as `artist==` quoted and `$1`, so the argument is quoted in place
+    let quote = |x: &String|
+    {
+        let mut ret     = String::new();
+        let mut last    = 0;
+
+        for m in ALIAS_PARAM.find_iter( x )
+        {
+            if m.start() > last
+            {
+                ret.push_str( &shell_words::quote( &x[ last .. m.start() ] ) );
+            }
+
+            ret.push_str( m.as_str() );
+            last = m.end();
+        }
+
+        if last < x.len() || x.is_empty()
+        {
+            ret.push_str( &shell_words::quote( &x[ last .. ] ) );
+        }
+
+        ret
+    };
+
+    words.iter().map( quote ).collect::< Vec<_> >().join( " " )
+}
+
+/// The command line an alias runs. `$1`..`$9` and `$@` in `body` are replaced by the arguments, quoted,
+/// and the arguments are appended when neither is used.
+pub fn expand_alias( body: &str, args: &[ String ] ) -> String
+{
+    let params : Vec<String> = args.iter().map( |x| shell_words::quote( x ).into_owned() ).collect();
+
+    let ret = ALIAS_PARAM.replace_all( body, |x: &regex::Captures|
+        {
+            match &x[1]
+            {
+                "@" => params.join( " " )
+            ,   n   => params.get( n.parse::<usize>().unwrap() - 1 ).cloned().unwrap_or_default()
+            }
+        }
+    ).into_owned();
+
+    if !ALIAS_PARAM.is_match( body ) && !params.is_empty()
+    {
+        format!( "{} {}", ret, params.join( " " ) )
+    }
+    else
+    {
+        ret
+    }
+}
+
+/// Where the output of a command goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Redirect
+{
+    /// `| command` ( run by the local shell )
+    Pipe( String )
+,   /// `> file`
+    Write( String )
+,   /// `>> file`
+    Append( String )
+}
+
+/// Splits off `| command`, `> file` or `>> file` outside of quotes and `$(…)`.
+/// `>` is a redirection only at the start of a word ( `date>=1960` is not ).
+pub fn split_redirect( cmd: &str ) -> ( &str, Option< Redirect > )
+{
+    let chars   : Vec<char>     = cmd.chars().collect();
+    let offsets : Vec<usize>    = cmd.char_indices().map( |x| x.0 ).collect();
+
+    let mut stack   = Vec::<char>::new();
+    let mut i       = 0;
+
+    while i < chars.len()
+    {
+        if stack.is_empty()
+        {
+            let head = &cmd[ .. offsets[i] ];
+
+            if chars[i] == '|'
+            {
+                return ( head, Some( Redirect::Pipe( String::from( cmd[ offsets[i] + 1 .. ].trim() ) ) ) );
+            }
+
+            if chars[i] == '>' && head.chars().last().is_none_or( char::is_whitespace )
+            {
+                return if chars.get( i + 1 ) == Some( &'>' )
+                {
+                    ( head, Some( Redirect::Append( String::from( cmd[ offsets[i] + 2 .. ].trim() ) ) ) )
+                }
+                else
+                {
+                    ( head, Some( Redirect::Write( String::from( cmd[ offsets[i] + 1 .. ].trim() ) ) ) )
+                };
+            }
+        }
+
+        i += step( &mut stack, &chars, i );
+    }
+
+    ( cmd, None )
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn chain( line: &str ) -> Vec< ( Chain, String ) >
+    {
+        split_chain( line ).into_iter().map( |( c, x )| ( c, String::from( x.trim() ) ) ).collect()
+    }
+
+    #[test]
+    fn split_chain_separators()
+    {
+        assert_eq!( chain( "cd Jazz; add && play || stop" ), vec![
+            ( Chain::Seq, String::from( "cd Jazz" ) )
+        ,   ( Chain::Seq, String::from( "add" ) )
+        ,   ( Chain::And, String::from( "play" ) )
+        ,   ( Chain::Or,  String::from( "stop" ) )
+        ] );
+    }
+
+    #[test]
+    fn split_chain_quotes_and_subst()
+    {
+        assert_eq!( chain( "ls \"a;b\" 'c && d'" ), vec![ ( Chain::Seq, String::from( "ls \"a;b\" 'c && d'" ) ) ] );
+        assert_eq!( chain( "add $(find a; b) ; play" ), vec![
+            ( Chain::Seq, String::from( "add $(find a; b)" ) )
+        ,   ( Chain::Seq, String::from( "play" ) )
+        ] );
+        assert_eq!( chain( r"ls a\;b" ), vec![ ( Chain::Seq, String::from( r"ls a\;b" ) ) ] );
+    }
+
+    #[test]
+    fn split_chain_comments()
+    {
+        assert_eq!( chain( "play # next; stop" ), vec![ ( Chain::Seq, String::from( "play" ) ) ] );
+        assert_eq!( chain( "ls a#b" ), vec![ ( Chain::Seq, String::from( "ls a#b" ) ) ] );
+        assert_eq!( chain( "ls '#a'" ), vec![ ( Chain::Seq, String::from( "ls '#a'" ) ) ] );
+        assert!( chain( "# all comment" ).is_empty() );
+        assert!( chain( " ; ;" ).is_empty() );
+    }
+
+    #[test]
+    fn expand_subst_words()
+    {
+        let run = |x: &str| if x == "find x" { Some( String::from( "a b\n\nc\n" ) ) } else { None };
+
+        assert_eq!( expand_subst( "add $(find x)", run ).as_deref(), Some( "add 'a b' c" ) );
+        assert_eq!( expand_subst( "echo \"$(find x)\"", run ).as_deref(), Some( "echo \"a b c\"" ) );
+        assert_eq!( expand_subst( "echo '$(find x)'", run ).as_deref(), Some( "echo '$(find x)'" ) );
+        assert_eq!( expand_subst( "add $(nope)", run ), None );
+    }
+
+    #[test]
+    fn expand_subst_nested()
+    {
+        let run = |x: &str| Some( format!( "[{}]", x ) );
+
+        assert_eq!( expand_subst( "a $(b $(c)) d", run ).as_deref(), Some( "a '[b $(c)]' d" ) );
+        assert_eq!( expand_subst( "a $(b", run ).as_deref(), Some( "a $(b" ) );
+    }
+
+    #[test]
+    fn last_command_for_completion()
+    {
+        assert_eq!( last_command( "cd Jazz; ls Li" ), " ls Li" );
+        assert_eq!( last_command( "ls \"a;b" ), "ls \"a;b" );
+        assert_eq!( last_command( "play &&" ), "" );
+    }
+
+    #[test]
+    fn word_start_for_completion()
+    {
+        assert_eq!( word_start( "ls Jazz/Mi", 2 ), 8 );
+        assert_eq!( word_start( "ls Jazz/Mi", 7 ), 3 );
+        assert_eq!( word_start( "ls Jazz/", 0 ), 8 );
+        assert_eq!( word_start( "ls ", 0 ), 3 );
+        assert_eq!( word_start( r"ls Album\ A", 7 ), 3 );
+        assert_eq!( word_start( r"ls Jazz/Kind\ of", 7 ), 8 );
+        assert_eq!( word_start( "ls \"Jazz/Kind of\"", 7 ), 9 );
+        assert_eq!( word_start( "ls 'Kind of'", 7 ), 3 );
+        assert_eq!( word_start( "ls Mi", 9 ), 3 );
+    }
+
+    fn words( x: &[ &str ] ) -> Vec<String>
+    {
+        x.iter().map( |x| String::from( *x ) ).collect()
+    }
+
+    #[test]
+    fn alias_body_keeps_the_quoting()
+    {
+        assert_eq!( alias_body( &words( &[ "cd", "Kind of Blue" ] ) ), "cd 'Kind of Blue'" );
+        assert_eq!( alias_body( &words( &[ "cd /Jazz; add; play" ] ) ), "cd /Jazz; add; play" );
+        assert_eq!( alias_body( &words( &[ "add", "$1", "$@" ] ) ), "add $1 $@" );
+        assert_eq!( alias_body( &words( &[ "find", "artist==$1", "" ] ) ), "find 'artist=='$1 ''" );
+        assert_eq!( alias_body( &words( &[ "cd", "$1/Disc 1" ] ) ), "cd $1'/Disc 1'" );
+
+        // the body splits into the words it was made of
+        let w = words( &[ "ls", "-l", "a \"b\" c", "it's" ] );
+
+        assert_eq!( shell_words::split( &alias_body( &w ) ).unwrap(), w );
+    }
+
+    #[test]
+    fn expand_alias_params()
+    {
+        assert_eq!( expand_alias( "cd $1; ls", &words( &[ "Kind of Blue" ] ) ), "cd 'Kind of Blue'; ls" );
+        assert_eq!( expand_alias( "add $@", &words( &[ "a", "b c" ] ) ), "add a 'b c'" );
+        assert_eq!( expand_alias( "add $2 $1 $3", &words( &[ "a", "b" ] ) ), "add b a " );
+        assert_eq!( expand_alias( "ls -l", &words( &[ "Jazz", "x y" ] ) ), "ls -l Jazz 'x y'" );
+        assert_eq!( expand_alias( "status", &[] ), "status" );
+        assert_eq!( expand_alias( "cd $1'/Disc 1'", &words( &[ "Kind of Blue" ] ) ), "cd 'Kind of Blue''/Disc 1'" );
+
+        let body = alias_body( &words( &[ "find", "artist==$1" ] ) );
+
+        assert_eq!( shell_words::split( &expand_alias( &body, &words( &[ "Miles Davis" ] ) ) ).unwrap(), words( &[ "find", "artist==Miles Davis" ] ) );
+
+        let body = alias_body( &words( &[ "cd", "Kind of Blue" ] ) );
+
+        assert_eq!( shell_words::split( &expand_alias( &body, &[] ) ).unwrap(), words( &[ "cd", "Kind of Blue" ] ) );
+    }
+
+    #[test]
+    fn split_redirect_targets()
+    {
+        assert_eq!( split_redirect( "pl -l | grep Miles" ), ( "pl -l ", Some( Redirect::Pipe( String::from( "grep Miles" ) ) ) ) );
+        assert_eq!( split_redirect( "ls > out.txt" ), ( "ls ", Some( Redirect::Write( String::from( "out.txt" ) ) ) ) );
+        assert_eq!( split_redirect( "ls >> out.txt" ), ( "ls ", Some( Redirect::Append( String::from( "out.txt" ) ) ) ) );
+        assert_eq!( split_redirect( "find date>=1960" ), ( "find date>=1960", None ) );
+        assert_eq!( split_redirect( "ls \"a | b\" '>c'" ), ( "ls \"a | b\" '>c'", None ) );
+        assert_eq!( split_redirect( "add $(ls | x)" ), ( "add $(ls | x)", None ) );
+    }
+}
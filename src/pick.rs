@@ -0,0 +1,241 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		pick.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::collections::HashMap;
+use std::io::{ self, Read, Write };
+use std::process::{ Command, Stdio };
+
+use rustyline::error::ReadlineError;
+
+/// The number of matches the built-in picker shows at once.
+const LIST_MAX : usize = 20;
+
+/// Something to pick. `label` is shown, `value` is printed when it is picked.
+pub struct Item
+{
+    pub label:  String
+,   pub value:  String
+}
+
+/// Scores `text` for one word of the query, as a subsequence ignoring case. None if it does not match.
+/// Consecutive characters, characters at the start of a word and a whole substring score more.
+fn score_term( term: &[char], text: &[char] ) -> Option< i64 >
+{
+    let mut score   = 0;
+    let mut ti      = 0;
+    let mut prev    = None;
+
+    for ( i, c ) in text.iter().enumerate()
+    {
+        if ti == term.len()
+        {
+            break;
+        }
+
+        if *c != term[ ti ]
+        {
+            continue;
+        }
+
+        score += 1;
+
+        if i > 0 && prev == Some( i - 1 )
+        {
+            score += 4;
+        }
+
+        if i == 0 || !text[ i - 1 ].is_alphanumeric()
+        {
+            score += 3;
+        }
+
+        prev = Some( i );
+        ti += 1;
+    }
+
+    if ti < term.len()
+    {
+        return None;
+    }
+
+    if text.windows( term.len() ).any( |x| x == term )
+    {
+        score += term.len() as i64 * 2;
+    }
+
+    Some( score )
+}
+
+/// Scores `text` for `query`. Every word of the query must match. Shorter texts win a tie.
+/// An empty query matches everything with the same score.
+pub fn fuzzy_score( query: &str, text: &str ) -> Option< i64 >
+{
+    if query.trim().is_empty()
+    {
+        return Some( 0 );
+    }
+
+    let text : Vec<char> = text.to_lowercase().chars().collect();
+
+    let mut score = 0;
+
+    for term in query.to_lowercase().split_whitespace()
+    {
+        let term : Vec<char> = term.chars().collect();
+
+        score += score_term( &term, &text )?;
+    }
+
+    Some( score * 64 - text.len() as i64 )
+}
+
+/// The indexes of the items matching `query`, best first.
+pub fn rank( items: &[ Item ], query: &str ) -> Vec< usize >
+{
+    let mut ret : Vec< ( i64, usize ) > = items.iter().enumerate()
+        .filter_map( |( i, x )| fuzzy_score( query, &x.label ).map( |s| ( s, i ) ) )
+        .collect();
+
+    ret.sort_by( |a, b| b.0.cmp( &a.0 ).then( a.1.cmp( &b.1 ) ) );
+
+    ret.into_iter().map( |x| x.1 ).collect()
+}
+
+/// Parses `1 3 5-7` ( 1 based ) into indexes below `count`.
+/// None if `input` is not a selection, Err if a number is out of range.
+fn parse_selection( input: &str, count: usize ) -> Option< Result< Vec< usize >, String > >
+{
+    let mut ret = Vec::< usize >::new();
+
+    for word in input.split( |c: char| c.is_whitespace() || c == ',' ).filter( |x| !x.is_empty() )
+    {
+        let ( a, b ) = match word.split_once( '-' )
+        {
+            Some( ( a, b ) )    => ( a.parse::<usize>().ok()?, b.parse::<usize>().ok()? )
+        ,   None                => { let x = word.parse::<usize>().ok()?; ( x, x ) }
+        };
+
+        if a == 0 || b < a || b > count
+        {
+            return Some( Err( format!( "out of range... {}", word ) ) );
+        }
+
+        for i in a - 1 .. b
+        {
+            if !ret.contains( &i )
+            {
+                ret.push( i );
+            }
+        }
+    }
+
+    Some( Ok( ret ) )
+}
+
+/// Runs an external picker with the labels on its stdin, and returns the indexes of the picked lines.
+/// With `cmd` None, runs `fzf -m` and returns Ok( None ) if it is not installed.
+pub fn external( cmd: Option< &str >, items: &[ Item ], ranked: &[ usize ] ) -> io::Result< Option< Vec< usize > > >
+{
+    let mut command = match cmd
+    {
+        Some( x ) =>
+        {
+            #[cfg(unix)]
+            let command = { let mut c = Command::new( "sh" ); c.arg( "-c" ).arg( x ); c };
+            #[cfg(windows)]
+            let command = { let mut c = Command::new( "cmd" ); c.arg( "/C" ).arg( x ); c };
+
+            command
+        }
+    ,   None => { let mut c = Command::new( "fzf" ); c.arg( "-m" ); c }
+    };
+
+    let mut child = match command.stdin( Stdio::piped() ).stdout( Stdio::piped() ).spawn()
+    {
+        Ok( x )     => x
+    ,   Err( x ) if cmd.is_none() && x.kind() == io::ErrorKind::NotFound => return Ok( None )
+    ,   Err( x )    => return Err( x )
+    };
+
+    if let Some( mut stdin ) = child.stdin.take()
+    {
+        for i in ranked
+        {
+            // the picker may quit before reading everything
+            if writeln!( stdin, "{}", items[ *i ].label ).is_err()
+            {
+                break;
+            }
+        }
+    }
+
+    let mut buf = String::new();
+
+    if let Some( mut x ) = child.stdout.take()
+    {
+        x.read_to_string( &mut buf )?;
+    }
+
+    child.wait()?;
+
+    let index : HashMap< &str, usize > = ranked.iter().map( |i| ( items[ *i ].label.as_str(), *i ) ).collect();
+
+    Ok( Some( buf.lines().filter_map( |x| index.get( x ).copied() ).collect() ) )
+}
+
+/// A line based picker on the terminal. Type words to filter, numbers to pick, nothing to cancel.
+pub fn builtin( items: &[ Item ], query: &str ) -> io::Result< Vec< usize > >
+{
+    let mut query   = String::from( query );
+    let mut rl      = rustyline::Editor::<()>::new().map_err( io::Error::other )?;
+
+    loop
+    {
+        let ranked = rank( items, &query );
+
+        eprintln!();
+
+        for ( n, i ) in ranked.iter().take( LIST_MAX ).enumerate()
+        {
+            eprintln!( "{:4}) {}", n + 1, items[ *i ].label );
+        }
+
+        if ranked.len() > LIST_MAX
+        {
+            eprintln!( "      ... {} more", ranked.len() - LIST_MAX );
+        }
+
+        eprintln!( "( words: filter, 1 3 5-7 or *: pick, empty: cancel )" );
+
+        let line = match rl.readline( &format!( "pick [{}]> ", query ) )
+        {
+            Ok( x )                                                     => x
+        ,   Err( ReadlineError::Interrupted | ReadlineError::Eof )      => return Ok( Vec::new() )
+        ,   Err( x )                                                    => return Err( io::Error::other( x ) )
+        };
+
+        let line = line.trim();
+
+        if line.is_empty()
+        {
+            return Ok( Vec::new() );
+        }
+
+        if line == "*"
+        {
+            return Ok( ranked );
+        }
+
+        match parse_selection( line, ranked.len().min( LIST_MAX ) )
+        {
+            Some( Ok( x ) )     => return Ok( x.into_iter().map( |n| ranked[ n ] ).collect() )
+        ,   Some( Err( x ) )    => eprintln!( "{}", x )
+        ,   None                => query = String::from( line )
+        }
+    }
+}
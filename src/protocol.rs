@@ -0,0 +1,245 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		protocol.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+
+use crate::error::{ Ack, AckCode };
+use crate::stream::MpdStream;
+
+/// A successful response. `flds` are the `key: value` lines in order, `bin` is the `binary:` payload.
+#[derive(Debug, Clone, Default)]
+pub struct ExecOk
+{
+    pub flds:   Vec<(String,String)>
+,   pub bin:    Option<Vec<u8>>
+}
+
+pub type ExecResult = Result<ExecOk, Ack>;
+
+/// Data and `type` of a binary object, `None` if MPD has none.
+pub type BinaryResult = Result< Option< ( Vec<u8>, Option<String> ) >, Ack >;
+
+/// Marks the end of each command's fields in a command_list_ok_begin response.
+pub const LIST_OK: &str = "list_OK";
+
+pub fn quote_arg( arg: &str ) -> String
+{
+    let mut arg = arg.replace('\\', r"\\").replace('"', r#"\""#);
+
+    if arg.contains( ' ' )
+    {
+        arg = String::from( "\"" ) + &arg + "\""
+    }
+
+    arg
+}
+
+pub fn make_command_1( cmd: &str, arg1: &str ) -> String
+{
+    let mut ret = String::from( cmd );
+    ret.push(' ');
+    ret.push_str( &quote_arg( arg1 ) );
+    ret
+}
+
+pub fn make_command_2( cmd: &str, arg1: &str, arg2: &str ) -> String
+{
+    let mut ret = String::from( cmd );
+    ret.push(' ');
+    ret.push_str( &quote_arg( arg1 ) );
+    ret.push(' ');
+    ret.push_str( &quote_arg( arg2 ) );
+    ret
+}
+
+/// Connects and reads the `OK MPD <version>` greeting.
+pub fn open<S: MpdStream>( addr: &S::Addr ) -> io::Result< ( BufReader<S>, String ) >
+{
+    let stream = S::connect( addr )?;
+
+    let mut reader = BufReader::new( stream );
+    let mut buf = String::new();
+
+    reader.read_line( &mut buf )?;
+
+    if !buf.starts_with("OK MPD ")
+    {
+        return Err( io::Error::new( io::ErrorKind::InvalidData, "not a MPD server" ) );
+    }
+
+    let version = String::from( buf[7..].trim() );
+
+    Ok( ( reader, version ) )
+}
+
+/// Sends one command and reads its response. `Err` means the connection is broken.
+pub fn transact<S: MpdStream>( reader: &mut BufReader<S>, cmd: &str, protolog: bool ) -> io::Result< ExecResult >
+{
+    let stream = reader.get_mut();
+
+    stream.write_all( cmd.as_bytes() )?;
+    stream.write_all( &[0x0a] )?;
+    stream.flush()?;
+
+    if protolog
+    {
+        if cmd.starts_with( "password " )
+        {
+            eprintln!( "> password ***" );
+        }
+        else
+        {
+            for x in cmd.lines()
+            {
+                eprintln!( "> {}", x );
+            }
+        }
+    }
+
+    read_response( reader, protolog )
+}
+
+/// Reads one response: fields up to `OK`, or an `ACK`. `list_OK` becomes a LIST_OK field. `Err` means the connection is broken.
+fn read_response<R: BufRead>( reader: &mut R, protolog: bool ) -> io::Result< ExecResult >
+{
+    let mut buf = String::new();
+
+    let mut flds            = Vec::<(String, String)>::new();
+    let mut bin             = Option::<Vec<u8>>::None;
+
+    loop
+    {
+        buf.clear();
+
+        if reader.read_line( &mut buf )? == 0
+        {
+            return Err( io::Error::new( io::ErrorKind::UnexpectedEof, "connection closed by MPD" ) );
+        }
+
+        if protolog
+        {
+            eprint!("< {}", buf );
+        }
+
+        if buf == "OK\n"
+        {
+            return Ok( Ok( ExecOk { flds, bin } ) );
+        }
+        else if buf == "list_OK\n"
+        {
+            flds.push( ( String::from( LIST_OK ), String::new() ) );
+        }
+        else if buf.starts_with( "ACK [" )
+        {
+            lazy_static! {
+                static ref RE: regex::Regex =
+                    regex::Regex::new( r"^ACK\s*\[(\d+)@(\d+)\]\s+\{([^}]*)\}\s*(.*)\n" ).unwrap();
+            }
+
+            let mut ack = Ack::new( AckCode::Other( 0 ), None );
+
+            if let Some( x ) = RE.captures( &buf )
+            {
+                ack.code        = AckCode::from_i32( x[1].parse().unwrap() );
+                ack.cmd_index   = x[2].parse().unwrap();
+                ack.cur_cmd     = Some( String::from( &x[3] ) );
+                ack.msg_text    = Some( String::from( &x[4] ) );
+            };
+
+            return Ok( Err( ack ) );
+        }
+        else
+        {
+            lazy_static! {
+                static ref RE: regex::Regex =
+                    regex::Regex::new( r"^([^:]*):\s*(.*)\n" ).unwrap();
+            }
+
+            if let Some( x ) = RE.captures( &buf )
+            {
+                if &x[1] == "binary"
+                {
+                    let binlen = match x[2].trim().parse::<usize>()
+                    {
+                        Ok( x )     => x
+                    ,   Err(_)      => return Err( io::Error::new( io::ErrorKind::InvalidData, "bad binary length" ) )
+                    };
+
+                    let mut buf = vec![ 0u8; binlen ];
+
+                    reader.read_exact( &mut buf )?;
+
+                    if protolog
+                    {
+                        eprintln!( "< [{} bytes]", binlen );
+                    }
+
+                    bin = Some( buf );
+                }
+                else
+                {
+                    flds.push(
+                        (
+                            String::from( x[1].trim() )
+                        ,   String::from( x[2].trim() )
+                        )
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn read( x: &str ) -> io::Result< ExecResult >
+    {
+        read_response( &mut x.as_bytes(), false )
+    }
+
+    #[test]
+    fn fields_and_list_ok()
+    {
+        let x = read( "file: a.flac\nTitle: So What: Live\nlist_OK\nlist_OK\nOK\n" ).unwrap().unwrap();
+
+        assert_eq!( x.flds, vec![
+            ( String::from( "file" ), String::from( "a.flac" ) )
+        ,   ( String::from( "Title" ), String::from( "So What: Live" ) )
+        ,   ( String::from( LIST_OK ), String::new() )
+        ,   ( String::from( LIST_OK ), String::new() )
+        ] );
+    }
+
+    #[test]
+    fn ack_in_the_middle_of_a_list()
+    {
+        let x = read( "list_OK\nlist_OK\nACK [50@2] {add} No such directory\n" ).unwrap().unwrap_err();
+
+        assert_eq!( x.code, AckCode::NoExist );
+        assert_eq!( x.cmd_index, 2 );
+        assert_eq!( x.cur_cmd.as_deref(), Some( "add" ) );
+        assert_eq!( x.msg_text.as_deref(), Some( "No such directory" ) );
+    }
+
+    #[test]
+    fn binary()
+    {
+        let x = read( "size: 5\nbinary: 3\nabc\nOK\n" ).unwrap().unwrap();
+
+        assert_eq!( x.bin.as_deref(), Some( &b"abc"[..] ) );
+        assert_eq!( x.flds, vec![ ( String::from( "size" ), String::from( "5" ) ) ] );
+
+        assert!( read( "binary: 9\nabc" ).is_err() );
+        assert!( read( "file: a" ).is_err() );
+    }
+}
@@ -0,0 +1,165 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		stream.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::io;
+use std::io::prelude::*;
+use std::net::TcpStream;
+use std::fmt;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use crate::client::{ Client, IdleNotify, ConnNotify };
+
+/// Address of a Unix domain socket. `@name` is an abstract socket ( Linux only ).
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub enum UnixAddr
+{
+    Path( String )
+,   Abstract( String )
+}
+
+#[cfg(unix)]
+impl fmt::Display for UnixAddr
+{
+    fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+    {
+        match self
+        {
+            UnixAddr::Path( x )     => write!( f, "{}", x )
+        ,   UnixAddr::Abstract( x ) => write!( f, "@{}", x )
+        }
+    }
+}
+
+/// A transport to MPD. The worker thread is generic over this,
+/// so every command behaves the same on TCP and Unix domain sockets.
+pub trait MpdStream : Read + Write + Send + Sized + 'static
+{
+    type Addr : fmt::Display + Clone + Send + 'static;
+
+    fn connect( addr: &Self::Addr ) -> io::Result< Self >;
+    fn shutdown_both( &self ) -> io::Result< () >;
+    fn try_clone( &self ) -> io::Result< Self >;
+}
+
+impl MpdStream for TcpStream
+{
+    type Addr = String;
+
+    fn connect( addr: &String ) -> io::Result< Self >
+    {
+        TcpStream::connect( addr )
+    }
+
+    fn shutdown_both( &self ) -> io::Result< () >
+    {
+        self.shutdown( std::net::Shutdown::Both )
+    }
+
+    fn try_clone( &self ) -> io::Result< Self >
+    {
+        TcpStream::try_clone( self )
+    }
+}
+
+#[cfg(unix)]
+impl MpdStream for UnixStream
+{
+    type Addr = UnixAddr;
+
+    fn connect( addr: &UnixAddr ) -> io::Result< Self >
+    {
+        match addr
+        {
+            UnixAddr::Path( x ) => UnixStream::connect( x )
+
+        ,   #[cfg(any(target_os = "linux", target_os = "android"))]
+            UnixAddr::Abstract( x ) =>
+            {
+                #[cfg(target_os = "linux")]
+                use std::os::linux::net::SocketAddrExt;
+                #[cfg(target_os = "android")]
+                use std::os::android::net::SocketAddrExt;
+
+                let sa = std::os::unix::net::SocketAddr::from_abstract_name( x.as_bytes() )?;
+                UnixStream::connect_addr( &sa )
+            }
+
+        ,   #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            UnixAddr::Abstract( _ ) =>
+            {
+                Err( io::Error::new( io::ErrorKind::Unsupported, "abstract sockets are not supported on this platform" ) )
+            }
+        }
+    }
+
+    fn shutdown_both( &self ) -> io::Result< () >
+    {
+        self.shutdown( std::net::Shutdown::Both )
+    }
+
+    fn try_clone( &self ) -> io::Result< Self >
+    {
+        UnixStream::try_clone( self )
+    }
+}
+
+/// Connection target. A host starting with `/` is a Unix domain socket path, `@name` is an abstract socket.
+#[derive(Debug, Clone)]
+pub enum ConnAddr
+{
+    Tcp( String )
+,   #[cfg(unix)]
+    Unix( UnixAddr )
+}
+
+impl ConnAddr
+{
+    pub fn new( host: &str, port: &str ) -> ConnAddr
+    {
+        #[cfg(unix)]
+        {
+            if host.starts_with( '/' )
+            {
+                return ConnAddr::Unix( UnixAddr::Path( String::from( host ) ) );
+            }
+            else if let Some( x ) = host.strip_prefix( '@' )
+            {
+                return ConnAddr::Unix( UnixAddr::Abstract( String::from( x ) ) );
+            }
+        }
+
+        ConnAddr::Tcp( format!( "{}:{}", host, port ) )
+    }
+
+    /// Connects with the stream type for this address. See Client::connect().
+    pub fn connect( &self, protolog: bool, notify: Option< IdleNotify >, conn_notify: Option< ConnNotify > ) -> io::Result< Client >
+    {
+        match self
+        {
+            ConnAddr::Tcp( x )  => Client::connect::< TcpStream >( x, protolog, notify, conn_notify )
+        ,   #[cfg(unix)]
+            ConnAddr::Unix( x ) => Client::connect::< UnixStream >( x, protolog, notify, conn_notify )
+        }
+    }
+}
+
+impl fmt::Display for ConnAddr
+{
+    fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+    {
+        match self
+        {
+            ConnAddr::Tcp( x )  => write!( f, "{}", x )
+        ,   #[cfg(unix)]
+            ConnAddr::Unix( x ) => write!( f, "{}", x )
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		tree.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::collections::BTreeMap;
+
+use mpdsh::{ EntryKind, ListEntry };
+
+use crate::glob::Pattern;
+
+/// A directory of the database built from `listall` / `listallinfo`, with the totals of everything under it.
+#[derive(Debug, Default)]
+pub struct Node
+{
+    /// The path from the root of the database.
+    pub path:       String
+,   pub dirs:       BTreeMap< String, Node >
+,   pub files:      Vec< ListEntry >
+,   /// The number of files under this directory.
+    pub count:      usize
+,   /// The total of the durations under this directory, in seconds.
+    pub duration:   f64
+}
+
+impl Node
+{
+    /// Builds the tree of `root` from the entries of `listall root`.
+    /// With `filter`, only the files matching it ( or in a matching directory ) are kept, and the directories without any are dropped.
+    pub fn build( root: &str, entries: Vec< ListEntry >, filter: Option< &Pattern > ) -> Node
+    {
+        let mut ret = Node { path: String::from( root ), ..Default::default() };
+
+        for entry in entries
+        {
+            let rel = if root.is_empty() { entry.name.as_str() } else { entry.name.strip_prefix( root ).and_then( |x| x.strip_prefix( '/' ) ).unwrap_or( "" ) };
+
+            if rel.is_empty()
+            {
+                continue;
+            }
+
+            let ( parents, name ) = match rel.rsplit_once( '/' )
+            {
+                Some( ( p, n ) )    => ( p.split( '/' ).collect(), n )
+            ,   None                => ( Vec::new(), rel )
+            };
+
+            let mut node = &mut ret;
+
+            for x in parents
+            {
+                node = node.dir( x );
+            }
+
+            match entry.kind
+            {
+                EntryKind::Directory                                                    => { node.dir( name ); }
+            ,   EntryKind::File if filter.is_none_or( |x| x.matches_within( &entry.name ) ) => node.files.push( entry )
+            ,   _                                                                       => {}
+            }
+        }
+
+        ret.total();
+
+        if filter.is_some()
+        {
+            ret.prune();
+        }
+
+        ret
+    }
+
+    /// The sub directory `name`, created if missing.
+    fn dir( &mut self, name: &str ) -> &mut Node
+    {
+        let path = if self.path.is_empty() { String::from( name ) } else { format!( "{}/{}", self.path, name ) };
+
+        self.dirs.entry( String::from( name ) ).or_insert_with( || Node { path, ..Default::default() } )
+    }
+
+    fn total( &mut self )
+    {
+        self.count      = self.files.len();
+        self.duration   = self.files.iter().filter_map( |x| x.get( "duration" ).and_then( |x| x.parse::<f64>().ok() ) ).sum();
+
+        for x in self.dirs.values_mut()
+        {
+            x.total();
+
+            self.count      += x.count;
+            self.duration   += x.duration;
+        }
+    }
+
+    fn prune( &mut self )
+    {
+        self.dirs.retain( |_, x| x.count != 0 );
+
+        for x in self.dirs.values_mut()
+        {
+            x.prune();
+        }
+    }
+
+    /// The last component of `path`.
+    pub fn name( &self ) -> &str
+    {
+        self.path.rsplit( '/' ).next().unwrap_or( &self.path )
+    }
+
+    /// The number of directories under this one.
+    pub fn dir_count( &self ) -> usize
+    {
+        self.dirs.values().map( |x| 1 + x.dir_count() ).sum()
+    }
+}
@@ -0,0 +1,443 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		types.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::fmt;
+use std::str::FromStr;
+
+type Fields = Vec<(String,String)>;
+
+/// Splits a response into records, each one starting at a field named in `keys`.
+/// Fields before the first key are dropped.
+pub fn split_records( flds: Fields, keys: &[&str] ) -> Vec< Fields >
+{
+    let mut ret = Vec::< Fields >::new();
+
+    for ( k, v ) in flds
+    {
+        if keys.contains( &k.as_str() )
+        {
+            ret.push( vec![ ( k, v ) ] );
+        }
+        else if let Some( x ) = ret.last_mut()
+        {
+            x.push( ( k, v ) );
+        }
+    }
+
+    ret
+}
+
+fn get_field<'a>( flds: &'a [(String,String)], key: &str ) -> Option< &'a str >
+{
+    flds.iter().find( |x| x.0 == key ).map( |x| x.1.as_str() )
+}
+
+fn parse_field<T: FromStr>( flds: &[(String,String)], key: &str ) -> Option< T >
+{
+    get_field( flds, key ).and_then( |x| x.parse::<T>().ok() )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind
+{
+    Directory
+,   File
+,   Playlist
+}
+
+impl EntryKind
+{
+    pub fn from_key( key: &str ) -> Option< EntryKind >
+    {
+        match key
+        {
+            "directory" => Some( EntryKind::Directory )
+        ,   "file"      => Some( EntryKind::File )
+        ,   "playlist"  => Some( EntryKind::Playlist )
+        ,   _           => None
+        }
+    }
+
+    pub fn as_str( &self ) -> &'static str
+    {
+        match self
+        {
+            EntryKind::Directory    => "directory"
+        ,   EntryKind::File         => "file"
+        ,   EntryKind::Playlist     => "playlist"
+        }
+    }
+}
+
+impl fmt::Display for EntryKind
+{
+    fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+    {
+        f.pad( self.as_str() )
+    }
+}
+
+/// An entry of lsinfo, listfiles, listall or listallinfo.
+#[derive(Debug, Clone)]
+pub struct ListEntry
+{
+    pub name:   String
+,   pub kind:   EntryKind
+,   pub flds:   Fields
+}
+
+impl ListEntry
+{
+    pub fn list_from_fields( flds: Fields ) -> Vec< ListEntry >
+    {
+        split_records( flds, &[ "directory", "file", "playlist" ] ).into_iter().map( |mut x|
+            {
+                let ( k, name ) = x.remove( 0 );
+
+                ListEntry { name, kind: EntryKind::from_key( &k ).unwrap(), flds: x }
+            }
+        ).collect()
+    }
+
+    pub fn get( &self, key: &str ) -> Option< &str >
+    {
+        get_field( &self.flds, key )
+    }
+
+    /// The last component of `name`.
+    pub fn base_name( &self ) -> &str
+    {
+        self.name.rsplit( '/' ).next().unwrap_or( &self.name )
+    }
+}
+
+/// A song of the queue, a search result or currentsong. `flds` holds every field but `file`.
+#[derive(Debug, Clone)]
+pub struct Song
+{
+    pub file:       String
+,   pub pos:        Option<u32>
+,   pub id:         Option<u32>
+,   pub duration:   Option<f64>
+,   pub flds:       Fields
+}
+
+impl Song
+{
+    pub fn from_fields( mut flds: Fields ) -> Option< Song >
+    {
+        let idx = flds.iter().position( |x| x.0 == "file" )?;
+        let ( _, file ) = flds.remove( idx );
+
+        Some(
+            Song
+            {
+                file
+            ,   pos:        parse_field( &flds, "Pos" )
+            ,   id:         parse_field( &flds, "Id" )
+            ,   duration:   parse_field( &flds, "duration" )
+            ,   flds
+            }
+        )
+    }
+
+    pub fn list_from_fields( flds: Fields ) -> Vec< Song >
+    {
+        split_records( flds, &[ "file" ] ).into_iter().filter_map( Song::from_fields ).collect()
+    }
+
+    /// A tag value. The tag name is not case sensitive.
+    pub fn tag( &self, name: &str ) -> Option< &str >
+    {
+        self.flds.iter().find( |x| x.0.eq_ignore_ascii_case( name ) ).map( |x| x.1.as_str() )
+    }
+
+    pub fn artist( &self ) -> Option< &str > { self.tag( "Artist" ) }
+    pub fn title( &self )  -> Option< &str > { self.tag( "Title" ) }
+    pub fn album( &self )  -> Option< &str > { self.tag( "Album" ) }
+
+    /// `Artist - Title`, the title, or the file name.
+    pub fn display_name( &self ) -> String
+    {
+        match ( self.artist(), self.title() )
+        {
+            ( Some( a ), Some( t ) )    => format!( "{} - {}", a, t )
+        ,   ( None, Some( t ) )         => String::from( t )
+        ,   _                           => self.file.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayState
+{
+    Play
+,   Pause
+,   Stop
+}
+
+impl fmt::Display for PlayState
+{
+    fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+    {
+        f.pad(
+            match self
+            {
+                PlayState::Play     => "play"
+            ,   PlayState::Pause    => "pause"
+            ,   PlayState::Stop     => "stop"
+            }
+        )
+    }
+}
+
+/// The response of `status`. `flds` keeps the raw fields.
+#[derive(Debug, Clone)]
+pub struct Status
+{
+    pub state:              PlayState
+,   pub volume:             Option<i32>
+,   pub repeat:             bool
+,   pub random:             bool
+,   pub single:             String
+,   pub consume:            String
+,   pub playlist:           u32
+,   pub playlist_length:    u32
+,   pub song:               Option<u32>
+,   pub song_id:            Option<u32>
+,   pub next_song:          Option<u32>
+,   pub next_song_id:       Option<u32>
+,   pub elapsed:            Option<f64>
+,   pub duration:           Option<f64>
+,   pub bitrate:            Option<u32>
+,   pub audio:              Option<String>
+,   pub updating_db:        Option<u32>
+,   pub error:              Option<String>
+,   pub flds:               Fields
+}
+
+impl Status
+{
+    pub fn from_fields( flds: Fields ) -> Status
+    {
+        Status
+        {
+            state: match get_field( &flds, "state" )
+            {
+                Some( "play" )  => PlayState::Play
+            ,   Some( "pause" ) => PlayState::Pause
+            ,   _               => PlayState::Stop
+            }
+        ,   volume:             parse_field( &flds, "volume" )
+        ,   repeat:             get_field( &flds, "repeat" ) == Some( "1" )
+        ,   random:             get_field( &flds, "random" ) == Some( "1" )
+        ,   single:             get_field( &flds, "single"  ).unwrap_or( "0" ).to_string()
+        ,   consume:            get_field( &flds, "consume" ).unwrap_or( "0" ).to_string()
+        ,   playlist:           parse_field( &flds, "playlist" ).unwrap_or( 0 )
+        ,   playlist_length:    parse_field( &flds, "playlistlength" ).unwrap_or( 0 )
+        ,   song:               parse_field( &flds, "song" )
+        ,   song_id:            parse_field( &flds, "songid" )
+        ,   next_song:          parse_field( &flds, "nextsong" )
+        ,   next_song_id:       parse_field( &flds, "nextsongid" )
+        ,   elapsed:            parse_field( &flds, "elapsed" )
+        ,   duration:           parse_field( &flds, "duration" )
+        ,   bitrate:            parse_field( &flds, "bitrate" )
+        ,   audio:              get_field( &flds, "audio" ).map( String::from )
+        ,   updating_db:        parse_field( &flds, "updating_db" )
+        ,   error:              get_field( &flds, "error" ).map( String::from )
+        ,   flds
+        }
+    }
+
+    /// A raw field, e.g. `get( "volume" )`.
+    pub fn get( &self, key: &str ) -> Option< &str >
+    {
+        get_field( &self.flds, key )
+    }
+}
+
+/// An audio output of `outputs`.
+#[derive(Debug, Clone)]
+pub struct Output
+{
+    pub id:         u32
+,   pub name:       String
+,   pub plugin:     Option<String>
+,   pub enabled:    bool
+}
+
+impl Output
+{
+    pub fn list_from_fields( flds: Fields ) -> Vec< Output >
+    {
+        split_records( flds, &[ "outputid" ] ).into_iter().map( |x|
+            {
+                Output
+                {
+                    id:         parse_field( &x, "outputid" ).unwrap_or( 0 )
+                ,   name:       get_field( &x, "outputname" ).unwrap_or( "" ).to_string()
+                ,   plugin:     get_field( &x, "plugin" ).map( String::from )
+                ,   enabled:    get_field( &x, "outputenabled" ) == Some( "1" )
+                }
+            }
+        ).collect()
+    }
+}
+
+/// A stored playlist of `listplaylists`.
+#[derive(Debug, Clone)]
+pub struct Playlist
+{
+    pub name:           String
+,   pub last_modified:  Option<String>
+}
+
+impl Playlist
+{
+    pub fn list_from_fields( flds: Fields ) -> Vec< Playlist >
+    {
+        split_records( flds, &[ "playlist" ] ).into_iter().map( |x|
+            {
+                Playlist
+                {
+                    name:           get_field( &x, "playlist" ).unwrap_or( "" ).to_string()
+                ,   last_modified:  get_field( &x, "Last-Modified" ).map( String::from )
+                }
+            }
+        ).collect()
+    }
+}
+
+/// The response of `stats`. Times are in seconds, `db_update` is a UNIX time.
+#[derive(Debug, Clone)]
+pub struct Stats
+{
+    pub artists:        u64
+,   pub albums:         u64
+,   pub songs:          u64
+,   pub uptime:         u64
+,   pub playtime:       u64
+,   pub db_playtime:    u64
+,   pub db_update:      u64
+}
+
+impl Stats
+{
+    pub fn from_fields( flds: Fields ) -> Stats
+    {
+        Stats
+        {
+            artists:        parse_field( &flds, "artists" ).unwrap_or( 0 )
+        ,   albums:         parse_field( &flds, "albums" ).unwrap_or( 0 )
+        ,   songs:          parse_field( &flds, "songs" ).unwrap_or( 0 )
+        ,   uptime:         parse_field( &flds, "uptime" ).unwrap_or( 0 )
+        ,   playtime:       parse_field( &flds, "playtime" ).unwrap_or( 0 )
+        ,   db_playtime:    parse_field( &flds, "db_playtime" ).unwrap_or( 0 )
+        ,   db_update:      parse_field( &flds, "db_update" ).unwrap_or( 0 )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn fields( x: &[ ( &str, &str ) ] ) -> Fields
+    {
+        x.iter().map( |( k, v )| ( String::from( *k ), String::from( *v ) ) ).collect()
+    }
+
+    #[test]
+    fn records_start_at_a_key()
+    {
+        let x = split_records( fields( &[ ( "x", "0" ), ( "file", "a" ), ( "Title", "A" ), ( "file", "b" ), ( "directory", "c" ), ( "y", "1" ) ] ), &[ "file", "directory" ] );
+
+        assert_eq!( x, vec![
+            fields( &[ ( "file", "a" ), ( "Title", "A" ) ] )
+        ,   fields( &[ ( "file", "b" ) ] )
+        ,   fields( &[ ( "directory", "c" ), ( "y", "1" ) ] )
+        ] );
+
+        assert!( split_records( fields( &[ ( "x", "0" ) ] ), &[ "file" ] ).is_empty() );
+    }
+
+    #[test]
+    fn song()
+    {
+        let x = Song::from_fields( fields( &[ ( "Artist", "A" ), ( "file", "d/s.flac" ), ( "Artist", "B" ), ( "Pos", "3" ), ( "Id", "x" ), ( "duration", "12.5" ), ( "Foo", "bar" ) ] ) ).unwrap();
+
+        assert_eq!( x.file, "d/s.flac" );
+        assert_eq!( ( x.pos, x.id, x.duration ), ( Some( 3 ), None, Some( 12.5 ) ) );
+
+        // every field but file, in order, the first of a repeated tag is the tag
+        assert_eq!( x.flds.len(), 6 );
+        assert_eq!( x.artist(), Some( "A" ) );
+        assert_eq!( x.tag( "foo" ), Some( "bar" ) );
+        assert_eq!( x.display_name(), "d/s.flac" );
+
+        assert!( Song::from_fields( fields( &[ ( "Title", "T" ), ( "Id", "1" ) ] ) ).is_none() );
+    }
+
+    #[test]
+    fn songs()
+    {
+        let x = Song::list_from_fields( fields( &[ ( "Title", "-" ), ( "file", "a" ), ( "Title", "A" ), ( "file", "b" ), ( "Artist", "B" ), ( "Title", "T" ) ] ) );
+
+        assert_eq!( x.len(), 2 );
+        assert_eq!( ( x[0].file.as_str(), x[0].display_name() ), ( "a", String::from( "A" ) ) );
+        assert_eq!( ( x[1].file.as_str(), x[1].display_name() ), ( "b", String::from( "B - T" ) ) );
+
+        assert!( Song::list_from_fields( Vec::new() ).is_empty() );
+    }
+
+    #[test]
+    fn status()
+    {
+        let x = Status::from_fields( fields( &[ ( "volume", "-1" ), ( "repeat", "1" ), ( "state", "pause" ), ( "song", "2" ), ( "elapsed", "1.5" ), ( "partition", "default" ) ] ) );
+
+        assert_eq!( x.state, PlayState::Pause );
+        assert_eq!( ( x.volume, x.repeat, x.random ), ( Some( -1 ), true, false ) );
+        assert_eq!( ( x.song, x.song_id, x.elapsed ), ( Some( 2 ), None, Some( 1.5 ) ) );
+        assert_eq!( x.get( "partition" ), Some( "default" ) );
+
+        let x = Status::from_fields( fields( &[ ( "state", "?" ), ( "playlistlength", "x" ) ] ) );
+
+        assert_eq!( x.state, PlayState::Stop );
+        assert_eq!( ( x.single.as_str(), x.consume.as_str(), x.playlist_length ), ( "0", "0", 0 ) );
+    }
+
+    #[test]
+    fn outputs()
+    {
+        let x = Output::list_from_fields( fields( &[
+            ( "outputid", "0" ), ( "outputname", "ALSA" ), ( "plugin", "alsa" ), ( "outputenabled", "1" ), ( "attribute", "dop=0" )
+        ,   ( "outputid", "1" ), ( "outputenabled", "0" )
+        ] ) );
+
+        assert_eq!( x.len(), 2 );
+        assert_eq!( ( x[0].id, x[0].name.as_str(), x[0].plugin.as_deref(), x[0].enabled ), ( 0, "ALSA", Some( "alsa" ), true ) );
+        assert_eq!( ( x[1].id, x[1].name.as_str(), x[1].plugin.as_deref(), x[1].enabled ), ( 1, "", None, false ) );
+    }
+
+    #[test]
+    fn list_entries()
+    {
+        let x = ListEntry::list_from_fields( fields( &[
+            ( "directory", "a/b" ), ( "Last-Modified", "t" )
+        ,   ( "file", "a/b.flac" ), ( "Title", "T" ), ( "Title", "U" )
+        ,   ( "playlist", "a/p.m3u" )
+        ] ) );
+
+        assert_eq!( x.iter().map( |x| x.kind ).collect::<Vec<_>>(), vec![ EntryKind::Directory, EntryKind::File, EntryKind::Playlist ] );
+        assert_eq!( x.iter().map( |x| x.base_name() ).collect::<Vec<_>>(), vec![ "b", "b.flac", "p.m3u" ] );
+        assert_eq!( x[0].get( "Last-Modified" ), Some( "t" ) );
+        assert_eq!( x[1].flds, fields( &[ ( "Title", "T" ), ( "Title", "U" ) ] ) );
+        assert!( x[2].flds.is_empty() );
+    }
+}
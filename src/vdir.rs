@@ -0,0 +1,255 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		vdir.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use mpdsh::{ Ack, AckCode, Client, EntryKind, ListEntry, Song };
+
+use crate::filter;
+
+/// The virtual directories at the root, and the tags they list.
+pub const ROOTS : &[ ( &str, &str ) ] = &[
+    ( ".artist",        "Artist" )
+,   ( ".albumartist",   "AlbumArtist" )
+,   ( ".genre",         "Genre" )
+,   ( ".date",          "Date" )
+,   ( ".composer",      "Composer" )
+];
+
+/// The virtual directory of the stored playlists.
+pub const PLAYLISTS : &str = ".playlists";
+
+/// A directory of the virtual trees, or a song in it.
+#[derive(Debug, Clone)]
+pub enum VirtualDir
+{
+    Tag( TagDir )
+,   /// `.playlists/<NAME>/<FILE NAME>`, built with `listplaylists` and `listplaylistinfo`.
+    Playlist
+    {
+        name:   Option<String>
+    ,   file:   Option<String>
+    }
+}
+
+/// A directory of the virtual tag tree, `.artist/<ARTIST>/<ALBUM>`, built with `list` and `find`.
+/// A song in an album directory is `.artist/<ARTIST>/<ALBUM>/<FILE NAME>`.
+#[derive(Debug, Clone)]
+pub struct TagDir
+{
+    pub tag:    &'static str
+,   pub value:  Option<String>
+,   pub album:  Option<String>
+,   pub file:   Option<String>
+}
+
+/// A tag value as a path component. `/` would split it, so it is written as `%2F` ( and `%` as `%25` ).
+pub fn encode( value: &str ) -> String
+{
+    value.replace( '%', "%25" ).replace( '/', "%2F" )
+}
+
+fn decode( name: &str ) -> String
+{
+    name.replace( "%2F", "/" ).replace( "%25", "%" )
+}
+
+fn no_exist() -> Ack
+{
+    Ack::new( AckCode::NoExist, Some( String::from( "No such directory" ) ) )
+}
+
+fn dirs( names: Vec<String> ) -> Vec< ListEntry >
+{
+    names.iter().map( |x| ListEntry { name: encode( x ), kind: EntryKind::Directory, flds: Vec::new() } ).collect()
+}
+
+fn songs_of( songs: Vec< Song >, file: &Option<String> ) -> Result< Vec< ListEntry >, Ack >
+{
+    let mut ret : Vec< ListEntry > = songs.into_iter().map( |x| ListEntry { name: x.file, kind: EntryKind::File, flds: x.flds } ).collect();
+
+    if let Some( x ) = file
+    {
+        ret.retain( |e| e.base_name() == x );
+
+        if ret.is_empty()
+        {
+            return Err( no_exist() );
+        }
+    }
+
+    Ok( ret )
+}
+
+impl VirtualDir
+{
+    /// Parses a canonical path ( without the leading `/` ). None if it is not in the virtual trees.
+    pub fn parse( path: &str ) -> Option< VirtualDir >
+    {
+        if path == PLAYLISTS || path.starts_with( ".playlists/" )
+        {
+            let parts : Vec<&str> = path.splitn( 3, '/' ).collect();
+
+            return Some(
+                VirtualDir::Playlist
+                {
+                    name:   parts.get( 1 ).map( |x| decode( x ) )
+                ,   file:   parts.get( 2 ).map( |x| String::from( *x ) )
+                }
+            );
+        }
+
+        let parts : Vec<&str> = path.splitn( 4, '/' ).collect();
+
+        let tag = ROOTS.iter().find( |x| x.0 == parts[0] )?.1;
+
+        Some(
+            VirtualDir::Tag(
+                TagDir
+                {
+                    tag
+                ,   value:  parts.get( 1 ).map( |x| decode( x ) )
+                ,   album:  parts.get( 2 ).map( |x| decode( x ) )
+                ,   file:   parts.get( 3 ).map( |x| String::from( *x ) )
+                }
+            )
+        )
+    }
+
+    /// True if this is a song, not a directory.
+    pub fn is_song( &self ) -> bool
+    {
+        match self
+        {
+            VirtualDir::Tag( x )                => x.file.is_some()
+        ,   VirtualDir::Playlist { file, .. }   => file.is_some()
+        }
+    }
+
+    /// The entries of this directory. Songs have their real path.
+    pub fn list( &self, client: &Client ) -> Result< Vec< ListEntry >, Ack >
+    {
+        match self
+        {
+            VirtualDir::Tag( x )                        => x.list( client )
+        ,   VirtualDir::Playlist { name: None, .. }     =>
+            {
+                Ok( client.playlists()?.into_iter().map( |x|
+                    {
+                        let flds = x.last_modified.map( |x| vec![ ( String::from( "Last-Modified" ), x ) ] ).unwrap_or_default();
+
+                        ListEntry { name: encode( &x.name ), kind: EntryKind::Directory, flds }
+                    }
+                ).collect() )
+            }
+        ,   VirtualDir::Playlist { .. }                 => self.songs( client )
+        }
+    }
+
+    /// The songs under this directory.
+    pub fn songs( &self, client: &Client ) -> Result< Vec< ListEntry >, Ack >
+    {
+        match self
+        {
+            VirtualDir::Tag( x )                                => x.songs( client )
+        ,   VirtualDir::Playlist { name: Some( x ), file }      => songs_of( client.playlist_songs( x )?, file )
+        ,   VirtualDir::Playlist { name: None, .. }             =>
+            {
+                let mut ret = Vec::< ListEntry >::new();
+
+                for x in client.playlists()?
+                {
+                    ret.extend( songs_of( client.playlist_songs( &x.name )?, &None )? );
+                }
+
+                Ok( ret )
+            }
+        }
+    }
+}
+
+impl TagDir
+{
+    /// The filter expression of the songs under this directory.
+    pub fn filter( &self ) -> String
+    {
+        let mut ret = Vec::<String>::new();
+
+        match &self.value
+        {
+            Some( x )   => ret.push( format!( "({} == {})", self.tag, filter::value( x ) ) )
+        ,   None        => ret.push( format!( "({} != \"\")", self.tag ) )
+        }
+
+        if let Some( x ) = &self.album
+        {
+            ret.push( format!( "(Album == {})", filter::value( x ) ) );
+        }
+
+        if ret.len() == 1
+        {
+            ret.remove( 0 )
+        }
+        else
+        {
+            format!( "({})", ret.join( " AND " ) )
+        }
+    }
+
+    fn find( client: &Client, filter: &str ) -> Result< Vec< ListEntry >, Ack >
+    {
+        songs_of( client.find( &[ String::from( filter ) ] )?, &None )
+    }
+
+    /// The entries of this directory: the tag values, the albums ( and the songs without one ), or the songs.
+    /// Songs have their real path.
+    pub fn list( &self, client: &Client ) -> Result< Vec< ListEntry >, Ack >
+    {
+        if self.value.is_none()
+        {
+            return Ok( dirs( client.list_tag( self.tag, None )? ) );
+        }
+
+        if self.album.is_none()
+        {
+            let filter = self.filter();
+
+            let mut ret = dirs( client.list_tag( "Album", Some( &filter ) )? );
+
+            ret.extend( Self::find( client, &format!( "({} AND (Album == \"\"))", filter ) )? );
+
+            return if ret.is_empty() { Err( no_exist() ) } else { Ok( ret ) };
+        }
+
+        self.songs( client )
+    }
+
+    /// The songs under this directory.
+    pub fn songs( &self, client: &Client ) -> Result< Vec< ListEntry >, Ack >
+    {
+        let ret = songs_of( client.find( &[ self.filter() ] )?, &self.file )?;
+
+        if ret.is_empty() && self.value.is_some()
+        {
+            return Err( no_exist() );
+        }
+
+        Ok( ret )
+    }
+}
+
+/// The virtual roots as directory entries, for completion.
+pub fn roots() -> Vec< ListEntry >
+{
+    ROOTS.iter().map( |x| x.0 ).chain( std::iter::once( PLAYLISTS ) )
+        .map( |x| ListEntry { name: String::from( x ), kind: EntryKind::Directory, flds: Vec::new() } ).collect()
+}
+
+/// True for a name of the virtual roots ( `.artist`, `.playlists`, ... ).
+pub fn is_root( name: &str ) -> bool
+{
+    name == PLAYLISTS || ROOTS.iter().any( |x| x.0 == name )
+}
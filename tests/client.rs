@@ -0,0 +1,137 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		client.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::io::{ BufRead, BufReader, Write };
+use std::net::TcpListener;
+use std::sync::{ Arc, Mutex };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use mpdsh::ConnAddr;
+
+type Log = Arc< Mutex< Vec< ( usize, String ) > > >;
+
+/// A tiny MPD. The first `changes` idles of a connection report a change, the next one waits until the connection closes.
+/// Returns the port and the commands received, by connection, with `<closed>` at the end of one.
+fn fake_mpd( changes: usize ) -> ( u16, Log )
+{
+    let listener    = TcpListener::bind( "127.0.0.1:0" ).unwrap();
+    let port        = listener.local_addr().unwrap().port();
+    let log         = Log::default();
+    let log2        = log.clone();
+
+    thread::spawn( move ||
+        {
+            for ( conn, stream ) in listener.incoming().flatten().enumerate()
+            {
+                let log = log2.clone();
+
+                thread::spawn( move ||
+                    {
+                        let mut writer  = stream.try_clone().unwrap();
+                        let mut idles   = 0;
+                        let _ = writer.write_all( b"OK MPD 0.23.5\n" );
+
+                        for line in BufReader::new( stream ).lines().map_while( Result::ok )
+                        {
+                            log.lock().unwrap().push( ( conn, line.clone() ) );
+
+                            let res = if line.starts_with( "idle" )
+                            {
+                                idles += 1;
+
+                                if idles > changes
+                                {
+                                    continue;
+                                }
+
+                                thread::sleep( Duration::from_millis( 100 ) );
+
+                                "changed: options\nOK\n"
+                            }
+                            else
+                            {
+                                "OK\n"
+                            };
+
+                            if writer.write_all( res.as_bytes() ).is_err()
+                            {
+                                break;
+                            }
+                        }
+
+                        log.lock().unwrap().push( ( conn, String::from( "<closed>" ) ) );
+                    }
+                );
+            }
+        }
+    );
+
+    ( port, log )
+}
+
+/// Waits up to 3 seconds for `f` to hold on the log.
+fn wait_for< F: Fn( &[ ( usize, String ) ] ) -> bool >( log: &Log, f: F ) -> bool
+{
+    let start = Instant::now();
+
+    while start.elapsed() < Duration::from_secs( 3 )
+    {
+        if f( &log.lock().unwrap() )
+        {
+            return true;
+        }
+
+        thread::sleep( Duration::from_millis( 20 ) );
+    }
+
+    false
+}
+
+/// The connection of the idle listener, the one that was sent `idle`.
+fn idle_conn( log: &[ ( usize, String ) ] ) -> Option< usize >
+{
+    log.iter().find( |x| x.1.starts_with( "idle" ) ).map( |x| x.0 )
+}
+
+#[test]
+fn dropping_the_client_ends_the_idle_listener()
+{
+    let ( port, log ) = fake_mpd( 0 );
+
+    let client = ConnAddr::new( "127.0.0.1", &port.to_string() ).connect( false, Some( Box::new( |_| {} ) ), None ).unwrap();
+
+    assert!( wait_for( &log, |x| idle_conn( x ).is_some() ) );
+
+    drop( client );
+
+    assert!( wait_for( &log, |x| x.iter().filter( |x| x.1 == "<closed>" ).count() == 2 ) );
+
+    // and it does not connect again
+    thread::sleep( Duration::from_millis( 700 ) );
+
+    assert!( log.lock().unwrap().iter().all( |x| x.0 < 2 ) );
+}
+
+#[test]
+fn the_idle_listener_sends_the_password_once()
+{
+    let ( port, log ) = fake_mpd( 3 );
+
+    let client = ConnAddr::new( "127.0.0.1", &port.to_string() ).connect( false, Some( Box::new( |_| {} ) ), None ).unwrap();
+
+    client.password( "secret" ).unwrap();
+
+    // the fourth idle waits
+    assert!( wait_for( &log, |x| x.iter().filter( |x| x.1.starts_with( "idle" ) ).count() == 4 ) );
+
+    let log = log.lock().unwrap();
+    let conn = idle_conn( &log ).unwrap();
+
+    assert_eq!( log.iter().filter( |x| x.0 == conn && x.1.starts_with( "password" ) ).count(), 1 );
+}
@@ -0,0 +1,113 @@
+//	vim:set ts=4 sw=4 sts=0 fileencoding=utf-8:
+//	----------------------------------------------------------------------------
+/*
+	@file		script.rs
+	@author		zuntan
+*/
+//	----------------------------------------------------------------------------
+
+use std::io::{ BufRead, BufReader, Write };
+use std::net::TcpListener;
+use std::process::{ Command, Output };
+use std::sync::{ Arc, Mutex };
+use std::thread;
+
+/// A tiny MPD: `/` holds `Jazz` and `Jazz` a file, anything else does not exist. Returns the port and the commands received.
+fn fake_mpd() -> ( u16, Arc< Mutex< Vec<String> > > )
+{
+    let listener    = TcpListener::bind( "127.0.0.1:0" ).unwrap();
+    let port        = listener.local_addr().unwrap().port();
+    let log         = Arc::new( Mutex::new( Vec::<String>::new() ) );
+    let log2        = log.clone();
+
+    thread::spawn( move ||
+        {
+            for stream in listener.incoming().flatten()
+            {
+                let log = log2.clone();
+
+                thread::spawn( move ||
+                    {
+                        let mut writer = stream.try_clone().unwrap();
+                        let _ = writer.write_all( b"OK MPD 0.23.5\n" );
+
+                        for line in BufReader::new( stream ).lines().map_while( Result::ok )
+                        {
+                            log.lock().unwrap().push( line.clone() );
+
+                            let res = match line.trim_end()
+                            {
+                                "listfiles"                     => "directory: Jazz\nOK\n"
+                            ,   "listfiles Jazz"                => "file: Jazz/a.flac\nOK\n"
+                            ,   x if x.starts_with( "listfiles" ) => "ACK [50@0] {listfiles} No such directory\n"
+                            ,   "close"                         => break
+                            ,   _                               => "OK\n"
+                            };
+
+                            if writer.write_all( res.as_bytes() ).is_err()
+                            {
+                                break;
+                            }
+                        }
+                    }
+                );
+            }
+        }
+    );
+
+    ( port, log )
+}
+
+fn run( port: u16, args: &[ &str ] ) -> Output
+{
+    let home = std::env::temp_dir().join( format!( "mpdsh-test-{}", std::process::id() ) );
+
+    Command::new( env!( "CARGO_BIN_EXE_mpdsh" ) )
+        .args( [ "-h", "127.0.0.1", "-p", &port.to_string() ] )
+        .args( args )
+        .env( "HOME", &home )
+        .env( "XDG_CONFIG_HOME", &home )
+        .env_remove( "MPD_HOST" )
+        .env_remove( "MPD_PORT" )
+        .output()
+        .unwrap()
+}
+
+fn sent( log: &Mutex< Vec<String> >, cmd: &str ) -> bool
+{
+    log.lock().unwrap().iter().any( |x| x == cmd || x.starts_with( &format!( "{} ", cmd ) ) )
+}
+
+#[test]
+fn stops_at_the_first_failed_command_of_a_line()
+{
+    let ( port, log ) = fake_mpd();
+
+    let out = run( port, &[ "-c", "cd Nope; add; play" ] );
+
+    assert!( !out.status.success() );
+    assert!( !sent( &log, "add" ) );
+    assert!( !sent( &log, "play" ) );
+}
+
+#[test]
+fn keep_going_runs_the_rest_and_still_fails()
+{
+    let ( port, log ) = fake_mpd();
+
+    let out = run( port, &[ "-k", "-c", "cd Nope; play" ] );
+
+    assert!( !out.status.success() );
+    assert!( sent( &log, "play" ) );
+}
+
+#[test]
+fn a_failure_handled_by_or_is_not_an_error()
+{
+    let ( port, log ) = fake_mpd();
+
+    let out = run( port, &[ "-c", "cd Nope || cd Jazz; play" ] );
+
+    assert!( out.status.success() );
+    assert!( sent( &log, "play" ) );
+}